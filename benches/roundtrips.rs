@@ -32,6 +32,65 @@ fn roundtrip(c: &mut Criterion) {
     group.finish();
 }
 
+fn roundtrip_utf8(c: &mut Criterion) {
+    let mut group = c.benchmark_group("raw+utf8");
+    group
+        .sample_size(50)
+        .measurement_time(Duration::from_secs(10));
+
+    for (name, sample) in SAMPLES.iter().copied() {
+        group.throughput(Throughput::Bytes(sample.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), sample, |b, s| {
+            b.iter(|| {
+                let encoded = zwc::encode_utf8(s.iter().copied());
+                let decoded = zwc::decode_utf8(encoded);
+                decoded.count();
+            });
+        });
+    }
+    group.finish();
+}
+
+fn roundtrip_bulk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("raw+bulk");
+    group
+        .sample_size(50)
+        .measurement_time(Duration::from_secs(10));
+
+    for (name, sample) in SAMPLES.iter().copied() {
+        group.throughput(Throughput::Bytes(sample.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), sample, |b, s| {
+            let mut encoded = vec![0; zwc::encoded_str_len(s.len())];
+            let mut decoded = vec![0; s.len()];
+            b.iter(|| {
+                let encoded = zwc::encode_to_str_buf(s, &mut encoded).unwrap();
+                zwc::decode_from_str(encoded, &mut decoded).unwrap();
+            });
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("compression+bulk");
+    group
+        .sample_size(40)
+        .measurement_time(Duration::from_secs(10));
+
+    for (name, sample) in SAMPLES.iter().copied() {
+        group.throughput(Throughput::Bytes(sample.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), sample, |b, s| {
+            let (compression, ..) = zwc::Compression::optimal(s);
+            let mut encoded = vec![0; zwc::encoded_compress_len(s, compression) * 3];
+            let mut decoded = vec![0; s.len()];
+            b.iter(|| {
+                let encoded =
+                    zwc::encode_compress_to_str_buf(s, &mut encoded, compression).unwrap();
+                zwc::decode_decompress_from_str(encoded, &mut decoded, compression).unwrap();
+            });
+        });
+    }
+    group.finish();
+}
+
 fn roundtrip_compression(c: &mut Criterion) {
     let mut group = c.benchmark_group("compression");
     group
@@ -105,6 +164,8 @@ fn roundtrip_camouflage(c: &mut Criterion) {
 criterion_group!(
     roundtrips,
     roundtrip,
+    roundtrip_utf8,
+    roundtrip_bulk,
     roundtrip_compression,
     roundtrip_camouflage,
 );
@@ -1,9 +1,17 @@
 use std::io::Write;
 use std::{
+    fmt,
     io::{self, Read},
     process,
+    str::FromStr,
 };
+
+/// Exit status for input data which can't be used, as defined by sysexits.h
+const DATA_ERROR: i32 = 65;
+/// Exit status for input or output failures, as defined by sysexits.h
+const IO_ERROR: i32 = 74;
 use structopt::StructOpt;
+use zwc::formats::Language;
 
 #[derive(StructOpt)]
 #[structopt(author, about)]
@@ -23,6 +31,41 @@ enum Opt {
         /// Brotli compression level of the payload, set to a sensible default if not specified
         #[structopt(short = "c", long = "compression-level", name = "LEVEL")]
         compression_level: Option<i32>,
+        /// Stream the payload instead of loading it in memory, the output can only be retrieved in streaming mode
+        #[structopt(short = "s", long = "stream")]
+        stream: bool,
+        /// Format of the dummy string, plain text if not specified
+        #[structopt(
+            short = "f",
+            long = "format",
+            name = "FORMAT",
+            possible_values = Format::VARIANTS,
+            conflicts_with = "stream",
+        )]
+        format: Option<Format>,
+        /// Programming language of the dummy string, required for the source format
+        #[structopt(
+            short = "l",
+            long = "language",
+            name = "LANGUAGE",
+            parse(try_from_str = parse_language),
+            required_if("FORMAT", "source"),
+        )]
+        language: Option<Language>,
+        /// Write zero-width characters as character references for the html format
+        #[structopt(short = "e", long = "entities")]
+        entities: bool,
+        /// Hex encoded Ed25519 secret key to sign the payload with, the payload is not signed if not specified
+        #[structopt(
+            long = "signing-key",
+            name = "SECRET_KEY",
+            parse(try_from_str = parse_key),
+            conflicts_with = "stream",
+        )]
+        signing_key: Option<[u8; 32]>,
+        /// Bind the payload to the visible text of the dummy string, so that it can't be moved to another one
+        #[structopt(short = "b", long = "bind", conflicts_with_all = &["stream", "FORMAT"])]
+        bind: bool,
     },
     /// Retrieves data from a string
     #[structopt(alias = "d")]
@@ -33,7 +76,137 @@ enum Opt {
         /// Decryption key, data is not decrypted if not specified
         #[structopt(short = "k", long = "key", name = "KEY")]
         key: Option<String>,
+        /// Stream the data instead of loading it in memory, for data hidden in streaming mode
+        #[structopt(short = "s", long = "stream")]
+        stream: bool,
+        /// Format of the string containing hidden data, plain text if not specified
+        #[structopt(
+            short = "f",
+            long = "format",
+            name = "FORMAT",
+            possible_values = Format::VARIANTS,
+            conflicts_with = "stream",
+        )]
+        format: Option<Format>,
+        /// Programming language of the string containing hidden data, required for the source format
+        #[structopt(
+            short = "l",
+            long = "language",
+            name = "LANGUAGE",
+            parse(try_from_str = parse_language),
+            required_if("FORMAT", "source"),
+        )]
+        language: Option<Language>,
     },
+    /// Retrieves signed data from a string and reports the public key of its signer
+    #[structopt(alias = "v")]
+    Verify {
+        /// String containing hidden signed data, read from standard input if not specified
+        #[structopt(name = "CAMOUFLAGED")]
+        camouflaged: Option<String>,
+        /// Decryption key, data is not decrypted if not specified
+        #[structopt(short = "k", long = "key", name = "KEY")]
+        key: Option<String>,
+        /// Format of the string containing hidden data, plain text if not specified
+        #[structopt(
+            short = "f",
+            long = "format",
+            name = "FORMAT",
+            possible_values = Format::VARIANTS
+        )]
+        format: Option<Format>,
+        /// Programming language of the string containing hidden data, required for the source format
+        #[structopt(
+            short = "l",
+            long = "language",
+            name = "LANGUAGE",
+            parse(try_from_str = parse_language),
+            required_if("FORMAT", "source"),
+        )]
+        language: Option<Language>,
+        /// Hex encoded Ed25519 public key the data must be signed with, any signer is accepted if not specified
+        #[structopt(long = "public-key", name = "PUBLIC_KEY", parse(try_from_str = parse_key))]
+        public_key: Option<[u8; 32]>,
+    },
+}
+
+/// Format of the string data is hidden in
+#[derive(Clone, Copy)]
+enum Format {
+    Text,
+    Email,
+    Html,
+    Json,
+    Markdown,
+    Source,
+    Toml,
+    Yaml,
+}
+impl Format {
+    const VARIANTS: &'static [&'static str] = &[
+        "text", "email", "html", "json", "markdown", "source", "toml", "yaml",
+    ];
+
+    /// Returns the corresponding document format, or `None` for plain text
+    fn document(self, language: Option<Language>, entities: bool) -> Option<zwc::formats::Format> {
+        use zwc::formats::Format as F;
+
+        match self {
+            Self::Text => None,
+            Self::Email => Some(F::Email),
+            Self::Html => Some(F::Html { entities }),
+            Self::Json => Some(F::Json),
+            Self::Markdown => Some(F::Markdown),
+            Self::Source => Some(F::Source(language.unwrap_or(Language::Rust))),
+            Self::Toml => Some(F::Toml),
+            Self::Yaml => Some(F::Yaml),
+        }
+    }
+}
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "email" => Ok(Self::Email),
+            "html" => Ok(Self::Html),
+            "json" => Ok(Self::Json),
+            "markdown" => Ok(Self::Markdown),
+            "source" => Ok(Self::Source),
+            "toml" => Ok(Self::Toml),
+            "yaml" => Ok(Self::Yaml),
+            _ => Err(format!("unknown format {}", s)),
+        }
+    }
+}
+
+fn parse_language(s: &str) -> Result<Language, String> {
+    match s {
+        "javascript" => Ok(Language::JavaScript),
+        "python" => Ok(Language::Python),
+        "rust" => Ok(Language::Rust),
+        _ => Language::from_extension(s).ok_or_else(|| format!("unknown language {}", s)),
+    }
+}
+
+fn parse_key(s: &str) -> Result<[u8; 32], String> {
+    let bytes = s.as_bytes();
+    if bytes.len() != 64 || !bytes.iter().all(u8::is_ascii_hexdigit) {
+        return Err("expected 64 hexadecimal digits".to_string());
+    }
+
+    let mut key = [0; 32];
+    for (k, pair) in key.iter_mut().zip(bytes.chunks(2)) {
+        *k = u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap();
+    }
+    Ok(key)
+}
+
+/// Prints an error and exits with the provided status
+fn fail<E: fmt::Display>(error: E, status: i32) -> ! {
+    eprintln!("{}", error);
+    process::exit(status)
 }
 
 fn main() {
@@ -44,8 +217,58 @@ fn main() {
             payload,
             compression_level,
             key,
-        } => camouflage(dummy, payload, compression_level, key),
-        Opt::Decamouflage { camouflaged, key } => decamouflage(camouflaged, key),
+            stream: false,
+            format,
+            language,
+            entities,
+            signing_key,
+            bind,
+        } => camouflage(
+            dummy,
+            payload,
+            compression_level,
+            key,
+            format.and_then(|f| f.document(language, entities)),
+            signing_key,
+            bind,
+        ),
+        Opt::Camouflage {
+            dummy,
+            payload,
+            compression_level,
+            key,
+            stream: true,
+            ..
+        } => camouflage_stream(dummy, payload, compression_level, key),
+        Opt::Decamouflage {
+            camouflaged,
+            key,
+            stream: false,
+            format,
+            language,
+        } => decamouflage(
+            camouflaged,
+            key,
+            format.and_then(|f| f.document(language, false)),
+        ),
+        Opt::Decamouflage {
+            camouflaged,
+            key,
+            stream: true,
+            ..
+        } => decamouflage_stream(camouflaged, key),
+        Opt::Verify {
+            camouflaged,
+            key,
+            format,
+            language,
+            public_key,
+        } => verify(
+            camouflaged,
+            key,
+            format.and_then(|f| f.document(language, false)),
+            public_key,
+        ),
     }
 }
 
@@ -54,45 +277,160 @@ fn camouflage(
     payload: Option<String>,
     compression_level: Option<i32>,
     key: Option<String>,
+    format: Option<zwc::formats::Format>,
+    signing_key: Option<[u8; 32]>,
+    bind: bool,
 ) {
     let payload = payload.map(|p| p.into_bytes()).unwrap_or_else(|| {
         let mut data = Vec::new();
         io::stdin()
             .lock()
             .read_to_end(&mut data)
-            .unwrap_or_else(|e| {
-                eprintln!("{}", e);
-                process::exit(74)
-            });
+            .unwrap_or_else(|e| fail(e, IO_ERROR));
+        data
+    });
+    let payload = match signing_key {
+        Some(k) => zwc::sign::sign(&payload, &k),
+        None => payload,
+    };
+
+    let key = key.as_ref().map(AsRef::as_ref);
+    match format {
+        Some(f) => match zwc::formats::embed(&dummy, f, &payload, key, compression_level) {
+            Ok(embedded) => print!("{}", embedded),
+            Err(e) => fail(e, DATA_ERROR),
+        },
+        None => {
+            let camouflaged = if bind {
+                zwc::camouflage_bound(payload, dummy.as_ref(), key, compression_level)
+            } else {
+                zwc::camouflage(payload, dummy.as_ref(), key, compression_level)
+            };
+            match camouflaged {
+                Ok(camouflaged) => println!("{}", camouflaged),
+                Err(e) => fail(e, DATA_ERROR),
+            }
+        }
+    }
+}
+
+fn decamouflage(
+    camouflaged: Option<String>,
+    key: Option<String>,
+    format: Option<zwc::formats::Format>,
+) {
+    let camouflaged = camouflaged.unwrap_or_else(|| {
+        let mut data = String::new();
+        io::stdin()
+            .lock()
+            .read_to_string(&mut data)
+            .unwrap_or_else(|e| fail(e, IO_ERROR));
         data
     });
 
-    match zwc::camouflage(
-        payload,
-        dummy.as_ref(),
-        key.as_ref().map(AsRef::as_ref),
-        compression_level,
-    ) {
-        Ok(camouflaged) => println!("{}", camouflaged),
-        Err(e) => eprintln!("{}", e),
+    let key = key.as_ref().map(AsRef::as_ref);
+    let payload = match format {
+        Some(f) => zwc::formats::extract(&camouflaged, f, key),
+        None => zwc::decamouflage(&camouflaged, key).map_err(Into::into),
+    };
+    match payload {
+        Ok(payload) => io::stdout()
+            .lock()
+            .write_all(&payload)
+            .unwrap_or_else(|e| fail(e, IO_ERROR)),
+        Err(e) => fail(e, DATA_ERROR),
     }
 }
 
-fn decamouflage(camouflaged: Option<String>, key: Option<String>) {
+fn verify(
+    camouflaged: Option<String>,
+    key: Option<String>,
+    format: Option<zwc::formats::Format>,
+    public_key: Option<[u8; 32]>,
+) {
     let camouflaged = camouflaged.unwrap_or_else(|| {
         let mut data = String::new();
         io::stdin()
             .lock()
             .read_to_string(&mut data)
-            .unwrap_or_else(|e| {
-                eprintln!("{}", e);
-                process::exit(74)
-            });
+            .unwrap_or_else(|e| fail(e, IO_ERROR));
         data
     });
 
-    match zwc::decamouflage(&camouflaged, key.as_ref().map(AsRef::as_ref)) {
-        Ok(payload) => io::stdout().lock().write_all(&payload).unwrap(),
-        Err(e) => eprintln!("{}", e),
+    let key = key.as_ref().map(AsRef::as_ref);
+    let container = match format {
+        Some(f) => zwc::formats::extract(&camouflaged, f, key),
+        None => zwc::decamouflage(&camouflaged, key).map_err(Into::into),
+    };
+    let container = container.unwrap_or_else(|e| fail(e, DATA_ERROR));
+    let signed = match public_key {
+        Some(k) => zwc::sign::open_from(&container, &k).map(|payload| zwc::sign::Signed {
+            payload,
+            public_key: k,
+        }),
+        None => zwc::sign::open(&container),
+    };
+    match signed {
+        Ok(signed) => {
+            let public_key: String = signed
+                .public_key
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            eprintln!("Signed by {}", public_key);
+            io::stdout()
+                .lock()
+                .write_all(&signed.payload)
+                .unwrap_or_else(|e| fail(e, IO_ERROR));
+        }
+        Err(e) => fail(e, DATA_ERROR),
+    }
+}
+
+fn camouflage_stream(
+    dummy: String,
+    payload: Option<String>,
+    compression_level: Option<i32>,
+    key: Option<String>,
+) {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let result = match payload {
+        Some(p) => zwc::camouflage_stream(
+            p.as_bytes(),
+            dummy.as_ref(),
+            key.as_ref().map(AsRef::as_ref),
+            compression_level,
+            stdout.lock(),
+        ),
+        None => zwc::camouflage_stream(
+            stdin.lock(),
+            dummy.as_ref(),
+            key.as_ref().map(AsRef::as_ref),
+            compression_level,
+            stdout.lock(),
+        ),
+    };
+
+    match result {
+        Ok(mut out) => writeln!(out).unwrap_or_else(|e| fail(e, IO_ERROR)),
+        Err(e) => fail(e, DATA_ERROR),
+    }
+}
+
+fn decamouflage_stream(camouflaged: Option<String>, key: Option<String>) {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let result = match camouflaged {
+        Some(c) => {
+            zwc::decamouflage_stream(c.as_bytes(), key.as_ref().map(AsRef::as_ref), stdout.lock())
+        }
+        None => {
+            zwc::decamouflage_stream(stdin.lock(), key.as_ref().map(AsRef::as_ref), stdout.lock())
+        }
+    };
+
+    if let Err(e) = result {
+        fail(e, DATA_ERROR)
     }
 }
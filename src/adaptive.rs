@@ -0,0 +1,204 @@
+use crate::{Block, Compression, Error, CHAR4, CHAR5};
+
+/// Number of bytes sharing the same compression settings in adaptive mode
+pub const ADAPTIVE_CHUNK_LEN: usize = 256;
+
+/// Converts a byte iterator into a zero-width character iterator compressed
+/// using the optimal settings for every chunk of [`ADAPTIVE_CHUNK_LEN`] bytes
+///
+/// Every chunk starts with a single character if the settings of the previous chunk are kept,
+/// or with a single character followed by the new settings encoded without compression if they change.
+/// Only a single chunk is ever buffered, so the input doesn't need to be available up front.
+pub fn encode_adaptive<T: Iterator<Item = u8>>(iter: T) -> EncodeAdaptiveIter<T> {
+    EncodeAdaptiveIter::new(iter)
+}
+
+/// Converts a zero-width character iterator produced by [`encode_adaptive`] into a byte iterator
+pub fn decode_adaptive<T: Iterator<Item = char>>(iter: T) -> DecodeAdaptiveIter<T> {
+    DecodeAdaptiveIter::new(iter)
+}
+
+/// Iterator converting bytes into zero-width characters compressed using the optimal settings for every chunk,
+/// returned by [`encode_adaptive`]
+#[derive(Debug, Clone)]
+pub struct EncodeAdaptiveIter<T: Iterator<Item = u8>> {
+    inner: T,
+    compression: Option<Compression>,
+    chunk: [u8; ADAPTIVE_CHUNK_LEN],
+    chunk_len: usize,
+    chunk_cursor: usize,
+    buffer: [char; 5],
+    buffer_len: usize,
+    cursor: usize,
+}
+impl<T: Iterator<Item = u8>> EncodeAdaptiveIter<T> {
+    /// Creates an adaptive encoding iterator over the provided bytes
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            compression: None,
+            chunk: [0; ADAPTIVE_CHUNK_LEN],
+            chunk_len: 0,
+            chunk_cursor: 0,
+            buffer: ['\0'; 5],
+            buffer_len: 0,
+            cursor: 0,
+        }
+    }
+
+    /// Reads the next chunk and writes its header to the buffer, returning `false` at the end of the input
+    fn next_chunk(&mut self) -> bool {
+        self.chunk_len = 0;
+        self.chunk_cursor = 0;
+        for (dst, b) in self.chunk.iter_mut().zip(self.inner.by_ref()) {
+            *dst = b;
+            self.chunk_len += 1;
+        }
+        if self.chunk_len == 0 {
+            return false;
+        }
+
+        let chunk = &self.chunk[..self.chunk_len];
+        let (optimal, rp0, rp1) = Compression::optimal(chunk);
+        // Changing settings costs 4 more characters than keeping them
+        let keep = match self.compression {
+            Some(current) => {
+                crate::encoded_compress_len(chunk, current)
+                    <= crate::encoded_compress_len(chunk, optimal) + 4
+            }
+            None => false,
+        };
+
+        if keep {
+            self.buffer[0] = CHAR4;
+            self.buffer_len = 1;
+        } else {
+            self.buffer[0] = CHAR5;
+            self.buffer[1..].copy_from_slice(&Block::from(rp0 | (rp1 << 4)).to_chars());
+            self.buffer_len = 5;
+            self.compression = Some(optimal);
+        }
+        true
+    }
+}
+impl<T: Iterator<Item = u8>> Iterator for EncodeAdaptiveIter<T> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor == self.buffer_len {
+            if self.chunk_cursor == self.chunk_len {
+                if !self.next_chunk() {
+                    return None;
+                }
+            } else {
+                let (chars, len) = Block::from(self.chunk[self.chunk_cursor])
+                    .to_compressed_chars(self.compression.unwrap());
+                self.buffer[..len].copy_from_slice(&chars[..len]);
+                self.buffer_len = len;
+                self.chunk_cursor += 1;
+            }
+            self.cursor = 0;
+        }
+
+        let ret = Some(self.buffer[self.cursor]);
+        self.cursor += 1;
+        ret
+    }
+}
+
+/// Iterator converting zero-width characters produced by [`encode_adaptive`] into bytes,
+/// returned by [`decode_adaptive`]
+#[derive(Debug, Clone)]
+pub struct DecodeAdaptiveIter<T: Iterator<Item = char>> {
+    inner: T,
+    compression: Option<Compression>,
+    remaining: usize,
+}
+impl<T: Iterator<Item = char>> DecodeAdaptiveIter<T> {
+    /// Creates an adaptive decoding iterator over the provided characters
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            compression: None,
+            remaining: 0,
+        }
+    }
+
+    /// Reads the header of the next chunk, returning `None` at the end of the input
+    fn next_chunk(&mut self) -> Option<Result<(), Error>> {
+        match self.inner.next()? {
+            CHAR4 if self.compression.is_some() => (),
+            CHAR5 => {
+                let mut chars = ['\0'; 4];
+                for (i, dst) in chars.iter_mut().enumerate() {
+                    *dst = match self.inner.next() {
+                        Some(c) => c,
+                        None => return Some(Err(Error::IncompleteBlock(i + 1))),
+                    };
+                }
+                let b = match Block::from_chars(chars) {
+                    Ok(b) => u8::from(b),
+                    Err(e) => return Some(Err(e)),
+                };
+                self.compression = Some(Compression::new(b & 0b0000_1111, b >> 4).unwrap());
+            }
+            c => return Some(Err(Error::InvalidCharacter(c))),
+        }
+
+        self.remaining = ADAPTIVE_CHUNK_LEN;
+        Some(Ok(()))
+    }
+}
+impl<T: Iterator<Item = char>> Iterator for DecodeAdaptiveIter<T> {
+    type Item = Result<u8, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            if let Err(e) = self.next_chunk()? {
+                return Some(Err(e));
+            }
+        }
+
+        self.remaining -= 1;
+        crate::decode_decompress(self.inner.by_ref(), self.compression.unwrap()).next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    static SAMPLE_HTML: &[u8] = include_bytes!("../samples/steganography.html");
+
+    #[test]
+    fn adaptive_round_trip() {
+        let encoded: Vec<char> = crate::encode_adaptive(SAMPLE_HTML.iter().copied()).collect();
+        let decoded: Result<Vec<u8>, _> = crate::decode_adaptive(encoded.into_iter()).collect();
+        assert_eq!(SAMPLE_HTML, decoded.unwrap().as_slice());
+
+        assert_eq!(crate::encode_adaptive(core::iter::empty()).count(), 0);
+    }
+
+    #[test]
+    fn adaptive_beats_global() {
+        let (compression, ..) = crate::Compression::optimal(SAMPLE_HTML);
+        let global = crate::encoded_compress_len(SAMPLE_HTML, compression);
+        let adaptive = crate::encode_adaptive(SAMPLE_HTML.iter().copied()).count();
+        assert!(adaptive < global);
+    }
+
+    #[test]
+    fn adaptive_errors() {
+        let mut decoded = crate::decode_adaptive([crate::CHARS[4]].iter().copied());
+        assert_eq!(
+            decoded.next(),
+            Some(Err(crate::Error::InvalidCharacter(crate::CHARS[4])))
+        );
+
+        // settings header cut off after two of its four characters
+        let truncated = [crate::CHARS[5], crate::CHARS[0], crate::CHARS[1]];
+        let mut decoded = crate::decode_adaptive(truncated.iter().copied());
+        assert_eq!(decoded.next(), Some(Err(crate::Error::IncompleteBlock(3))));
+
+        let mut decoded = crate::decode_adaptive(crate::CHARS[5..].iter().copied());
+        assert_eq!(decoded.next(), Some(Err(crate::Error::IncompleteBlock(1))));
+    }
+}
@@ -0,0 +1,390 @@
+#[cfg(feature = "async")]
+use crate::io::{decode_chunk, invalid_data};
+use crate::io::{encode_chunk, DecodeState};
+use crate::Compression;
+#[cfg(feature = "async")]
+use bytes::{Buf, Bytes, BytesMut};
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+#[cfg(feature = "async")]
+use tokio::io::ReadBuf;
+#[cfg(feature = "async")]
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Asynchronous writer encoding written bytes as UTF-8 zero-width characters into an underlying writer
+///
+/// Implements the tokio `AsyncWrite` trait with the `async` feature and the futures one with the `futures-io` feature.
+#[derive(Debug)]
+pub struct AsyncZwEncoder<W> {
+    inner: W,
+    compression: Option<Compression>,
+    buffer: Vec<u8>,
+    written: usize,
+}
+impl<W> AsyncZwEncoder<W> {
+    /// Creates an encoder writing to the provided writer without compression
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            compression: None,
+            buffer: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Creates an encoder writing to the provided writer compressed using the provided settings
+    pub fn with_compression(inner: W, compression: Compression) -> Self {
+        Self {
+            inner,
+            compression: Some(compression),
+            buffer: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Returns a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying writer
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the underlying writer, discarding encoded data which wasn't flushed yet
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Writes the pending encoded data to the underlying writer using the provided write function
+    fn poll_drain<F>(&mut self, cx: &mut Context<'_>, mut write: F) -> Poll<io::Result<()>>
+    where
+        F: FnMut(&mut W, &mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>,
+    {
+        while self.written < self.buffer.len() {
+            let pending = &self.buffer[self.written..];
+            match ready!(write(&mut self.inner, cx, pending))? {
+                0 => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                written => self.written += written,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Encodes bytes once the pending encoded data was written using the provided write function
+    fn poll_encode<F>(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        write: F,
+    ) -> Poll<io::Result<usize>>
+    where
+        F: FnMut(&mut W, &mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>,
+    {
+        ready!(self.poll_drain(cx, write))?;
+
+        // The encoded data is kept around and written on the next call or when flushing
+        let len = encode_chunk(buf, &mut self.buffer, self.compression)?;
+        self.written = 0;
+        Poll::Ready(Ok(len))
+    }
+}
+#[cfg(feature = "async")]
+impl<W: tokio::io::AsyncWrite + Unpin> tokio::io::AsyncWrite for AsyncZwEncoder<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut()
+            .poll_encode(cx, buf, |w, cx, buf| Pin::new(w).poll_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx, |w, cx, buf| Pin::new(w).poll_write(cx, buf)))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx, |w, cx, buf| Pin::new(w).poll_write(cx, buf)))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+#[cfg(feature = "futures-io")]
+impl<W: futures_io::AsyncWrite + Unpin> futures_io::AsyncWrite for AsyncZwEncoder<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut()
+            .poll_encode(cx, buf, |w, cx, buf| Pin::new(w).poll_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx, |w, cx, buf| Pin::new(w).poll_write(cx, buf)))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx, |w, cx, buf| Pin::new(w).poll_write(cx, buf)))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+/// Asynchronous reader decoding UTF-8 zero-width characters read from an underlying reader
+///
+/// Implements the tokio `AsyncRead` trait with the `async` feature and the futures one with the `futures-io` feature.
+#[derive(Debug)]
+pub struct AsyncZwDecoder<R> {
+    inner: R,
+    state: DecodeState,
+}
+impl<R> AsyncZwDecoder<R> {
+    /// Creates a decoder reading from the provided reader without compression
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: DecodeState::new(None),
+        }
+    }
+
+    /// Creates a decoder reading from the provided reader decompressed using the provided settings
+    pub fn with_compression(inner: R, compression: Compression) -> Self {
+        Self {
+            inner,
+            state: DecodeState::new(Some(compression)),
+        }
+    }
+
+    /// Returns a reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the underlying reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads from the underlying reader using the provided read function until decoded output is available
+    fn poll_fill<F>(&mut self, cx: &mut Context<'_>, mut read: F) -> Poll<io::Result<()>>
+    where
+        F: FnMut(&mut R, &mut Context<'_>, &mut [u8]) -> Poll<io::Result<usize>>,
+    {
+        while !self.state.has_output() && !self.state.is_eof() {
+            let read = ready!(read(&mut self.inner, cx, self.state.unfilled()))?;
+            self.state.filled(read)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+#[cfg(feature = "async")]
+impl<R: tokio::io::AsyncRead + Unpin> tokio::io::AsyncRead for AsyncZwDecoder<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_fill(cx, |r, cx, unfilled| {
+            let mut input = ReadBuf::new(unfilled);
+            ready!(Pin::new(r).poll_read(cx, &mut input))?;
+            Poll::Ready(Ok(input.filled().len()))
+        }))?;
+
+        let len = this.state.read_output(buf.initialize_unfilled());
+        buf.advance(len);
+        Poll::Ready(Ok(()))
+    }
+}
+#[cfg(feature = "futures-io")]
+impl<R: futures_io::AsyncRead + Unpin> futures_io::AsyncRead for AsyncZwDecoder<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_fill(cx, |r, cx, unfilled| Pin::new(r).poll_read(cx, unfilled)))?;
+        Poll::Ready(Ok(this.state.read_output(buf)))
+    }
+}
+
+/// Codec encoding and decoding frames of bytes as UTF-8 zero-width characters
+#[cfg(feature = "async")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZwCodec {
+    compression: Option<Compression>,
+}
+#[cfg(feature = "async")]
+impl ZwCodec {
+    /// Creates a codec without compression
+    pub fn new() -> Self {
+        Self { compression: None }
+    }
+
+    /// Creates a codec compressing using the provided settings
+    pub fn with_compression(compression: Compression) -> Self {
+        Self {
+            compression: Some(compression),
+        }
+    }
+
+    /// Decodes the buffered input, returning `None` if no complete block is available
+    fn decode_buf(&mut self, src: &mut BytesMut, eof: bool) -> io::Result<Option<Bytes>> {
+        let mut decoded = Vec::new();
+        let len = decode_chunk(src, eof, &mut decoded, self.compression)?;
+        src.advance(len);
+
+        if decoded.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(decoded.into()))
+        }
+    }
+}
+#[cfg(feature = "async")]
+impl Decoder for ZwCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.decode_buf(src, false)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.decode_buf(src, true)
+    }
+}
+#[cfg(feature = "async")]
+impl<T: AsRef<[u8]>> Encoder<T> for ZwCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let data = item.as_ref();
+        let start = dst.len();
+        dst.resize(start + crate::encoded_str_len(data.len()), 0);
+
+        let len = match self.compression {
+            Some(compression) => {
+                crate::encode_compress_to_str_buf(data, &mut dst[start..], compression)
+            }
+            None => crate::encode_to_str_buf(data, &mut dst[start..]),
+        }
+        .map_err(invalid_data)?
+        .len();
+        dst.truncate(start + len);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    static SRC: &[u8] = include_bytes!("./async_io.rs");
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_round_trip() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut encoder = crate::AsyncZwEncoder::new(Vec::new());
+        encoder.write_all(SRC).await.unwrap();
+        encoder.shutdown().await.unwrap();
+        let encoded = encoder.into_inner();
+        assert!(std::str::from_utf8(&encoded)
+            .unwrap()
+            .chars()
+            .eq(crate::encode(SRC.iter().copied())));
+
+        let mut decoded = Vec::new();
+        crate::AsyncZwDecoder::new(encoded.as_slice())
+            .read_to_end(&mut decoded)
+            .await
+            .unwrap();
+        assert_eq!(SRC, decoded.as_slice());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn compression_async_round_trip() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (compression, ..) = crate::Compression::optimal(SRC);
+        let (client, server) = tokio::io::duplex(64);
+
+        let writer = tokio::spawn(async move {
+            let mut encoder = crate::AsyncZwEncoder::with_compression(client, compression);
+            encoder.write_all(SRC).await.unwrap();
+            encoder.shutdown().await.unwrap();
+        });
+        let mut decoded = Vec::new();
+        crate::AsyncZwDecoder::with_compression(server, compression)
+            .read_to_end(&mut decoded)
+            .await
+            .unwrap();
+        writer.await.unwrap();
+        assert_eq!(SRC, decoded.as_slice());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn codec_partial_frames() {
+        use bytes::BytesMut;
+        use tokio_util::codec::{Decoder, Encoder};
+
+        let mut codec = crate::ZwCodec::new();
+        let mut encoded = BytesMut::new();
+        codec.encode(b"hello", &mut encoded).unwrap();
+
+        let mut src = BytesMut::from(&encoded[..20]);
+        assert_eq!(codec.decode(&mut src).unwrap().as_deref(), Some(&b"h"[..]));
+        src.extend_from_slice(&encoded[20..]);
+        assert_eq!(
+            codec.decode(&mut src).unwrap().as_deref(),
+            Some(&b"ello"[..])
+        );
+        assert!(codec.decode_eof(&mut src).unwrap().is_none());
+
+        let mut src = BytesMut::from(&encoded[..20]);
+        codec.decode(&mut src).unwrap();
+        assert!(codec.decode_eof(&mut src).is_err());
+    }
+
+    #[cfg(feature = "futures-io")]
+    #[test]
+    fn futures_round_trip() {
+        use futures::io::{AsyncReadExt, AsyncWriteExt, Cursor};
+
+        let (compression, ..) = crate::Compression::optimal(SRC);
+        futures::executor::block_on(async {
+            let mut encoder =
+                crate::AsyncZwEncoder::with_compression(Cursor::new(Vec::new()), compression);
+            encoder.write_all(SRC).await.unwrap();
+            encoder.close().await.unwrap();
+            let encoded = encoder.into_inner().into_inner();
+            assert!(std::str::from_utf8(&encoded)
+                .unwrap()
+                .chars()
+                .eq(crate::encode_compress(SRC.iter().copied(), compression)));
+
+            let mut decoded = Vec::new();
+            crate::AsyncZwDecoder::with_compression(encoded.as_slice(), compression)
+                .read_to_end(&mut decoded)
+                .await
+                .unwrap();
+            assert_eq!(SRC, decoded.as_slice());
+        });
+    }
+}
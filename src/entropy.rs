@@ -0,0 +1,313 @@
+use crate::huffman::{digit, huffman_lengths, Canonical, MAX_LEN};
+use crate::{Error, CHARS};
+use core::iter::Copied;
+use core::slice;
+
+/// Number of byte values
+const BYTES: usize = 256;
+/// Longest run of absent symbols serialized at once
+const MAX_RUN: usize = 36;
+
+/// Represents a variable-length code mapping bytes to 1 to 5 zero-width characters
+///
+/// Codes are canonical, so they can be fully described by their lengths,
+/// and bytes with a length of 0 are absent from the code.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ByteCode(Canonical<BYTES>);
+impl ByteCode {
+    /// Creates a canonical code from the codeword length of each byte
+    pub fn from_lengths(lengths: [u8; BYTES]) -> Result<Self, Error> {
+        Canonical::from_lengths(lengths).map(Self)
+    }
+
+    /// Creates a length-limited code for the provided data
+    /// and returns it along with the encoded length in characters, excluding the code table
+    ///
+    /// The code is a Huffman code of the byte frequencies, which are halved until no codeword is longer than
+    /// 5 characters. This is a heuristic rather than an optimal length-limited code like package-merge would find,
+    /// although both match whenever the plain Huffman code already fits.
+    pub fn optimal(data: &[u8]) -> (Self, usize) {
+        let mut frequencies = [0; BYTES];
+        for b in data.iter().copied() {
+            frequencies[b as usize] += 1;
+        }
+
+        let mut symbols = [0; BYTES];
+        let mut weights = [0; BYTES];
+        let mut n = 0;
+        for (b, f) in frequencies.iter().enumerate().filter(|(_, f)| **f != 0) {
+            symbols[n] = b;
+            weights[n] = *f;
+            n += 1;
+        }
+
+        // Frequencies are scaled down until the code fits, which flattens the tree
+        // and ends up with codewords of at most 4 characters for uniform weights
+        let mut used_lengths = [0; BYTES];
+        loop {
+            huffman_lengths(&weights[..n], &mut used_lengths[..n]);
+            if used_lengths[..n].iter().all(|l| *l as usize <= MAX_LEN) {
+                break;
+            }
+            for w in weights[..n].iter_mut() {
+                *w = w.div_ceil(2);
+            }
+        }
+
+        let mut lengths = [0; BYTES];
+        for (s, l) in symbols[..n].iter().zip(used_lengths[..n].iter()) {
+            lengths[*s] = *l;
+        }
+        let code = Self::from_lengths(lengths).unwrap();
+        let len = code.encoded_len(data);
+        (code, len)
+    }
+
+    /// Returns the codeword length of each byte
+    pub fn lengths(&self) -> [u8; BYTES] {
+        self.0.lengths()
+    }
+
+    /// Returns the number of zero-width characters required to encode the provided data, excluding the code table
+    pub fn encoded_len(&self, data: &[u8]) -> usize {
+        data.iter().map(|b| self.0.len(*b)).sum()
+    }
+
+    /// Returns the number of zero-width characters required to serialize the code table
+    pub fn table_len(&self) -> usize {
+        self.table().count()
+    }
+
+    /// Returns an iterator over the serialized code table
+    ///
+    /// Lengths are written as a single digit each, and runs of absent bytes
+    /// as a 0 digit followed by the length of the run minus one as two digits.
+    fn table(&self) -> TableIter {
+        TableIter {
+            lengths: self.lengths(),
+            cursor: 0,
+            buffer: ['\0'; 3],
+            buffer_len: 0,
+            buffer_cursor: 0,
+        }
+    }
+
+    /// Reads a serialized code table from a character iterator
+    fn read_table<T: Iterator<Item = char>>(iter: &mut T) -> Result<Self, Error> {
+        let mut lengths = [0; BYTES];
+        let mut read = 0;
+        let mut next = || -> Result<usize, Error> {
+            let c = iter.next().ok_or(Error::IncompleteBlock(read))?;
+            read += 1;
+            digit(c)
+        };
+
+        let mut cursor = 0;
+        while cursor < BYTES {
+            match next()? {
+                0 => {
+                    let run = next()? * CHARS.len() + next()? + 1;
+                    if cursor + run > BYTES {
+                        return Err(Error::InvalidCodeLengths);
+                    }
+                    cursor += run;
+                }
+                l => {
+                    lengths[cursor] = l as u8;
+                    cursor += 1;
+                }
+            }
+        }
+        Self::from_lengths(lengths)
+    }
+}
+
+/// Converts bytes into a zero-width character iterator using a variable-length code fitted to them,
+/// preceded by the serialized code table
+pub fn encode_entropy(data: &[u8]) -> EncodeEntropyIter<'_> {
+    EncodeEntropyIter::new(data)
+}
+
+/// Converts a zero-width character iterator produced by [`encode_entropy`] into a byte iterator
+pub fn decode_entropy<T: Iterator<Item = char>>(iter: T) -> DecodeEntropyIter<T> {
+    DecodeEntropyIter::new(iter)
+}
+
+/// Serialized code table iterator
+#[derive(Debug, Clone)]
+struct TableIter {
+    lengths: [u8; BYTES],
+    cursor: usize,
+    buffer: [char; 3],
+    buffer_len: usize,
+    buffer_cursor: usize,
+}
+impl Iterator for TableIter {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer_cursor == self.buffer_len {
+            if self.cursor == BYTES {
+                return None;
+            }
+
+            match self.lengths[self.cursor] {
+                0 => {
+                    let run = self.lengths[self.cursor..]
+                        .iter()
+                        .take(MAX_RUN)
+                        .take_while(|l| **l == 0)
+                        .count();
+                    self.buffer = [
+                        CHARS[0],
+                        CHARS[(run - 1) / CHARS.len()],
+                        CHARS[(run - 1) % CHARS.len()],
+                    ];
+                    self.buffer_len = 3;
+                    self.cursor += run;
+                }
+                l => {
+                    self.buffer[0] = CHARS[l as usize];
+                    self.buffer_len = 1;
+                    self.cursor += 1;
+                }
+            }
+            self.buffer_cursor = 0;
+        }
+
+        let ret = Some(self.buffer[self.buffer_cursor]);
+        self.buffer_cursor += 1;
+        ret
+    }
+}
+
+/// Iterator converting bytes into zero-width characters using a variable-length code fitted to them,
+/// returned by [`encode_entropy`]
+#[derive(Debug, Clone)]
+pub struct EncodeEntropyIter<'a> {
+    table: TableIter,
+    inner: Copied<slice::Iter<'a, u8>>,
+    code: ByteCode,
+    buffer: [char; MAX_LEN],
+    buffer_len: usize,
+    cursor: usize,
+}
+impl<'a> EncodeEntropyIter<'a> {
+    /// Creates an encoding iterator over the provided bytes, computing a code for them using [`ByteCode::optimal`]
+    pub fn new(data: &'a [u8]) -> Self {
+        let (code, _) = ByteCode::optimal(data);
+        Self {
+            table: code.table(),
+            inner: data.iter().copied(),
+            code,
+            buffer: ['\0'; MAX_LEN],
+            buffer_len: 0,
+            cursor: 0,
+        }
+    }
+}
+impl Iterator for EncodeEntropyIter<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(c) = self.table.next() {
+            return Some(c);
+        }
+        if self.cursor == self.buffer_len {
+            self.buffer_len = self
+                .code
+                .0
+                .write_codeword(self.inner.next()?, &mut self.buffer);
+            self.cursor = 0;
+        }
+
+        let ret = Some(self.buffer[self.cursor]);
+        self.cursor += 1;
+        ret
+    }
+}
+
+/// Iterator converting zero-width characters produced by [`encode_entropy`] into bytes,
+/// returned by [`decode_entropy`]
+#[derive(Debug, Clone)]
+pub struct DecodeEntropyIter<T: Iterator<Item = char>> {
+    inner: T,
+    code: Option<ByteCode>,
+    failed: bool,
+}
+impl<T: Iterator<Item = char>> DecodeEntropyIter<T> {
+    /// Creates a decoding iterator over the provided characters, starting with the serialized code table
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            code: None,
+            failed: false,
+        }
+    }
+}
+impl<T: Iterator<Item = char>> Iterator for DecodeEntropyIter<T> {
+    type Item = Result<u8, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let code = match self.code {
+            Some(code) => code,
+            None => match ByteCode::read_table(&mut self.inner) {
+                Ok(code) => *self.code.insert(code),
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            },
+        };
+
+        let ret = code.0.read_symbol(&mut self.inner, &mut 0);
+        self.failed = matches!(ret, Some(Err(_)));
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    static SRC: &[u8] = include_bytes!("./entropy.rs");
+    static LOREM: &[u8] = include_bytes!("../samples/lorem.txt");
+
+    #[test]
+    fn entropy_round_trip() {
+        for data in [SRC, LOREM, b"", b"aaaa"].iter() {
+            let encoded: Vec<char> = crate::encode_entropy(data).collect();
+            let (code, len) = crate::ByteCode::optimal(data);
+            assert_eq!(encoded.len(), code.table_len() + len);
+
+            let decoded: Result<Vec<u8>, _> = crate::decode_entropy(encoded.into_iter()).collect();
+            assert_eq!(*data, decoded.unwrap().as_slice());
+        }
+    }
+
+    #[test]
+    fn entropy_beats_compression() {
+        let (compression, ..) = crate::Compression::optimal(LOREM);
+        let compressed = crate::encoded_compress_len(LOREM, compression);
+        assert!(crate::encode_entropy(LOREM).count() * 3 < compressed * 2);
+
+        let (code, _) = crate::ByteCode::optimal(&(0..=255).collect::<Vec<u8>>());
+        assert!(code.lengths().iter().all(|l| (1..=5).contains(l)));
+    }
+
+    #[test]
+    fn entropy_errors() {
+        let encoded: Vec<char> = crate::encode_entropy(LOREM).collect();
+        let decoded: Result<Vec<u8>, _> =
+            crate::decode_entropy(encoded[..10].iter().copied()).collect();
+        assert!(matches!(decoded, Err(crate::Error::IncompleteBlock(_))));
+
+        // A run of absent bytes going past the end of the table
+        let table = [crate::CHARS[0], crate::CHARS[5], crate::CHARS[5]].repeat(8);
+        let mut decoded = crate::decode_entropy(table.into_iter());
+        assert_eq!(decoded.next(), Some(Err(crate::Error::InvalidCodeLengths)));
+        assert_eq!(decoded.next(), None);
+    }
+}
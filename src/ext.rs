@@ -0,0 +1,269 @@
+#[cfg(feature = "alloc")]
+use crate::Error;
+use crate::{
+    Compression, DecodeAdaptiveIter, DecodeDecompressIter, DecodeEntropyIter, DecodeHuffmanIter,
+    DecodeIter, EncodeAdaptiveIter, EncodeCompressIter, EncodeEntropyIter, EncodeHuffmanIter,
+    EncodeIter, NibbleCode,
+};
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+use core::iter::Copied;
+use core::slice;
+use core::str::Chars;
+
+/// Extension methods encoding byte slices, including `Vec<u8>` through deref
+pub trait ZwEncodeExt {
+    /// Returns an iterator over the zero-width characters encoding the bytes
+    fn zw_chars(&self) -> EncodeIter<Copied<slice::Iter<'_, u8>>>;
+
+    /// Returns an iterator over the zero-width characters encoding the bytes compressed using the provided settings
+    fn zw_chars_compressed(
+        &self,
+        compression: Compression,
+    ) -> EncodeCompressIter<Copied<slice::Iter<'_, u8>>>;
+
+    /// Returns an iterator over the zero-width characters encoding the bytes using the optimal variable-length code
+    /// for them, preceded by the serialized code table
+    fn zw_chars_entropy(&self) -> EncodeEntropyIter<'_>;
+
+    /// Encodes the bytes into a zero-width string
+    #[cfg(feature = "alloc")]
+    fn to_zw(&self) -> String;
+
+    /// Encodes the bytes into a zero-width string compressed using the provided settings
+    #[cfg(feature = "alloc")]
+    fn to_zw_compressed(&self, compression: Compression) -> String;
+}
+impl ZwEncodeExt for [u8] {
+    fn zw_chars(&self) -> EncodeIter<Copied<slice::Iter<'_, u8>>> {
+        EncodeIter::new(self.iter().copied())
+    }
+
+    fn zw_chars_compressed(
+        &self,
+        compression: Compression,
+    ) -> EncodeCompressIter<Copied<slice::Iter<'_, u8>>> {
+        EncodeCompressIter::new(self.iter().copied(), compression)
+    }
+
+    fn zw_chars_entropy(&self) -> EncodeEntropyIter<'_> {
+        EncodeEntropyIter::new(self)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn to_zw(&self) -> String {
+        crate::encode_to_string(self)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn to_zw_compressed(&self, compression: Compression) -> String {
+        crate::encode_compress_to_string(self, compression)
+    }
+}
+
+/// Extension methods encoding byte iterators
+pub trait ZwEncodeIterExt: Iterator<Item = u8> + Sized {
+    /// Converts the iterator into a zero-width character iterator
+    fn zw_encode(self) -> EncodeIter<Self> {
+        EncodeIter::new(self)
+    }
+
+    /// Converts the iterator into a zero-width character iterator compressed using the provided settings
+    fn zw_encode_compressed(self, compression: Compression) -> EncodeCompressIter<Self> {
+        EncodeCompressIter::new(self, compression)
+    }
+
+    /// Converts the iterator into a zero-width character iterator using the provided variable-length code
+    fn zw_encode_huffman(self, code: NibbleCode) -> EncodeHuffmanIter<Self> {
+        EncodeHuffmanIter::new(self, code)
+    }
+
+    /// Converts the iterator into a zero-width character iterator compressed using the optimal settings for every chunk
+    fn zw_encode_adaptive(self) -> EncodeAdaptiveIter<Self> {
+        EncodeAdaptiveIter::new(self)
+    }
+
+    /// Encodes the bytes into a zero-width string
+    #[cfg(feature = "alloc")]
+    fn to_zw(self) -> String {
+        self.zw_encode().collect()
+    }
+
+    /// Encodes the bytes into a zero-width string compressed using the provided settings
+    #[cfg(feature = "alloc")]
+    fn to_zw_compressed(self, compression: Compression) -> String {
+        self.zw_encode_compressed(compression).collect()
+    }
+}
+impl<T: Iterator<Item = u8>> ZwEncodeIterExt for T {}
+
+/// Extension methods decoding zero-width strings
+pub trait ZwDecodeExt {
+    /// Returns an iterator over the bytes decoded from the string
+    fn zw_bytes(&self) -> DecodeIter<Chars<'_>>;
+
+    /// Returns an iterator over the bytes decoded from the string decompressed using the provided settings
+    fn zw_bytes_decompressed(&self, compression: Compression) -> DecodeDecompressIter<Chars<'_>>;
+
+    /// Decodes the string into bytes
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::wrong_self_convention)]
+    fn from_zw(&self) -> Result<Vec<u8>, Error>;
+
+    /// Decodes the string into bytes decompressed using the provided settings
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::wrong_self_convention)]
+    fn from_zw_decompressed(&self, compression: Compression) -> Result<Vec<u8>, Error>;
+}
+impl ZwDecodeExt for str {
+    fn zw_bytes(&self) -> DecodeIter<Chars<'_>> {
+        DecodeIter::new(self.chars())
+    }
+
+    fn zw_bytes_decompressed(&self, compression: Compression) -> DecodeDecompressIter<Chars<'_>> {
+        DecodeDecompressIter::new(self.chars(), compression)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn from_zw(&self) -> Result<Vec<u8>, Error> {
+        // The bulk decoder works on UTF-8 bytes, so errors are reported again in terms of characters
+        crate::decode_to_vec(self).or_else(|_| self.chars().from_zw())
+    }
+
+    #[cfg(feature = "alloc")]
+    fn from_zw_decompressed(&self, compression: Compression) -> Result<Vec<u8>, Error> {
+        crate::decode_decompress_to_vec(self, compression)
+            .or_else(|_| self.chars().from_zw_decompressed(compression))
+    }
+}
+
+/// Extension methods decoding zero-width character iterators
+///
+/// Decoded bytes can be collected into a `Result<Vec<u8>, Error>`, stopping at the first error.
+pub trait ZwDecodeIterExt: Iterator<Item = char> + Sized {
+    /// Converts the iterator into a byte iterator
+    fn zw_decode(self) -> DecodeIter<Self> {
+        DecodeIter::new(self)
+    }
+
+    /// Converts the iterator into a byte iterator decompressed using the provided settings
+    fn zw_decode_decompressed(self, compression: Compression) -> DecodeDecompressIter<Self> {
+        DecodeDecompressIter::new(self, compression)
+    }
+
+    /// Converts the iterator into a byte iterator using the provided variable-length code
+    fn zw_decode_huffman(self, code: NibbleCode) -> DecodeHuffmanIter<Self> {
+        DecodeHuffmanIter::new(self, code)
+    }
+
+    /// Converts the iterator, starting with a serialized code table, into a byte iterator
+    fn zw_decode_entropy(self) -> DecodeEntropyIter<Self> {
+        DecodeEntropyIter::new(self)
+    }
+
+    /// Converts the iterator, compressed using the optimal settings for every chunk, into a byte iterator
+    fn zw_decode_adaptive(self) -> DecodeAdaptiveIter<Self> {
+        DecodeAdaptiveIter::new(self)
+    }
+
+    /// Decodes the characters into bytes
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::wrong_self_convention)]
+    fn from_zw(self) -> Result<Vec<u8>, Error> {
+        self.zw_decode().collect()
+    }
+
+    /// Decodes the characters into bytes decompressed using the provided settings
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::wrong_self_convention)]
+    fn from_zw_decompressed(self, compression: Compression) -> Result<Vec<u8>, Error> {
+        self.zw_decode_decompressed(compression).collect()
+    }
+}
+impl<T: Iterator<Item = char>> ZwDecodeIterExt for T {}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::{ZwDecodeExt, ZwDecodeIterExt, ZwEncodeExt, ZwEncodeIterExt};
+
+    static SRC: &[u8] = include_bytes!("./ext.rs");
+
+    #[test]
+    fn ext_round_trip() {
+        let encoded = SRC.to_zw();
+        assert!(encoded.chars().eq(SRC.zw_chars()));
+        assert_eq!(encoded, SRC.to_vec().to_zw());
+        assert_eq!(encoded, SRC.iter().copied().to_zw());
+
+        assert_eq!(SRC, encoded.from_zw().unwrap().as_slice());
+        assert_eq!(SRC, encoded.chars().from_zw().unwrap().as_slice());
+        assert!(encoded
+            .zw_bytes()
+            .map(Result::unwrap)
+            .eq(SRC.iter().copied()));
+    }
+
+    #[test]
+    fn compression_ext_round_trip() {
+        let (compression, ..) = crate::Compression::optimal(SRC);
+        let encoded = SRC.to_zw_compressed(compression);
+        assert_eq!(encoded, SRC.iter().copied().to_zw_compressed(compression));
+
+        assert_eq!(
+            SRC,
+            encoded
+                .from_zw_decompressed(compression)
+                .unwrap()
+                .as_slice()
+        );
+        assert_eq!(
+            SRC,
+            encoded
+                .chars()
+                .from_zw_decompressed(compression)
+                .unwrap()
+                .as_slice()
+        );
+    }
+
+    #[test]
+    fn codec_ext_round_trip() {
+        let (code, _) = crate::NibbleCode::optimal(SRC);
+        let decoded: Result<Vec<u8>, _> = SRC
+            .iter()
+            .copied()
+            .zw_encode_huffman(code)
+            .zw_decode_huffman(code)
+            .collect();
+        assert_eq!(SRC, decoded.unwrap().as_slice());
+
+        let decoded: Result<Vec<u8>, _> = SRC.zw_chars_entropy().zw_decode_entropy().collect();
+        assert_eq!(SRC, decoded.unwrap().as_slice());
+
+        let decoded: Result<Vec<u8>, _> = SRC
+            .iter()
+            .copied()
+            .zw_encode_adaptive()
+            .zw_decode_adaptive()
+            .collect();
+        assert_eq!(SRC, decoded.unwrap().as_slice());
+    }
+
+    #[test]
+    fn ext_errors() {
+        assert_eq!("abcd".from_zw(), Err(crate::Error::InvalidCharacter('a')));
+        assert_eq!(
+            "\u{200C}\u{200E}\u{200C}\u{200C}".from_zw(),
+            Err(crate::Error::InvalidCharacter('\u{200E}'))
+        );
+        let (compression, ..) = crate::Compression::optimal(SRC);
+        assert_eq!(
+            "abcd".from_zw_decompressed(compression),
+            Err(crate::Error::InvalidCharacter('a'))
+        );
+        assert_eq!(
+            "abcd".chars().from_zw(),
+            Err(crate::Error::InvalidCharacter('a'))
+        );
+    }
+}
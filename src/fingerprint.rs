@@ -0,0 +1,212 @@
+//! Traceable copies of a document, identifying the recipient a leaked copy was given to
+//!
+//! Each copy carries the same short mark many times, at the end of every sentence and line, so that the recipient
+//! can still be identified from an excerpt. Marks are made of a tag, which is an HMAC of the recipient identifier,
+//! followed by the identifier encrypted using a keystream derived from the key and the tag, so that recipients can't
+//! read each other's identifiers. Only the length of the identifier is visible.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+#[cfg(feature = "std")]
+mod tardos;
+
+#[cfg(feature = "std")]
+pub use tardos::{Accused, Tardos};
+
+/// Represents an error that might occur while fingerprinting a document
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    NoStrings,
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoStrings => write!(f, "no text in document to hide marks in"),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Length of the tag following the identifier in a mark
+const TAG_LEN: usize = 8;
+
+/// Produces one copy of a document per recipient, each carrying the identifier of its recipient
+pub fn fingerprint<S: AsRef<str>>(
+    document: &str,
+    recipients: &[S],
+    key: &str,
+) -> Result<Vec<String>, Error> {
+    let positions = positions(document);
+    if positions.is_empty() {
+        return Err(Error::NoStrings);
+    }
+
+    Ok(recipients
+        .iter()
+        .map(|r| {
+            let mark = mark(r.as_ref(), key);
+            let mut copy = String::with_capacity(document.len() + mark.len() * positions.len());
+            let mut cursor = 0;
+            for position in &positions {
+                copy.push_str(&document[cursor..*position]);
+                copy.push_str(&mark);
+                cursor = *position;
+            }
+            copy.push_str(&document[cursor..]);
+            copy
+        })
+        .collect())
+}
+
+/// Recovers the identifier of the recipient a copy produced using [`fingerprint`] was given to
+///
+/// Marks which were damaged or made using another key are ignored,
+/// and the identifier carried by the most marks is returned if there are several.
+pub fn identify(leaked: &str, key: &str) -> Option<String> {
+    let mut votes: BTreeMap<String, usize> = BTreeMap::new();
+    for run in leaked.split(|c| !crate::is_zw(c)).filter(|r| !r.is_empty()) {
+        if let Some(recipient) = parse_mark(run, key) {
+            *votes.entry(recipient).or_default() += 1;
+        }
+    }
+    votes
+        .into_iter()
+        .max_by_key(|(_, v)| *v)
+        .map(|(recipient, _)| recipient)
+}
+
+/// Encodes the mark of a recipient
+fn mark(recipient: &str, key: &str) -> String {
+    let tag = tag(recipient.as_bytes(), key);
+    let mut bytes = Vec::with_capacity(TAG_LEN + recipient.len());
+    bytes.extend_from_slice(&tag);
+    bytes.extend_from_slice(recipient.as_bytes());
+    apply_keystream(&mut bytes[TAG_LEN..], &tag, key);
+    crate::encode_to_string(&bytes)
+}
+
+/// Decodes a mark, returning the identifier it carries if its tag is valid
+fn parse_mark(run: &str, key: &str) -> Option<String> {
+    let mut bytes = crate::decode_to_vec(run).ok()?;
+    if bytes.len() < TAG_LEN {
+        return None;
+    }
+    let (expected, recipient) = bytes.split_at_mut(TAG_LEN);
+    apply_keystream(recipient, expected, key);
+    if tag(recipient, key) != *expected {
+        return None;
+    }
+    String::from_utf8(recipient.to_vec()).ok()
+}
+
+/// Creates an HMAC instance for one use of the key
+fn hmac(key: &str, domain: u8) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(&[domain]);
+    mac
+}
+
+/// Computes the tag of an identifier
+fn tag(recipient: &[u8], key: &str) -> [u8; TAG_LEN] {
+    let mut mac = hmac(key, 0);
+    mac.update(recipient);
+    let mut tag = [0; TAG_LEN];
+    tag.copy_from_slice(&mac.finalize().into_bytes()[..TAG_LEN]);
+    tag
+}
+
+/// Encrypts or decrypts an identifier using a keystream derived from the key and the tag of the identifier,
+/// so that different identifiers are never encrypted using the same keystream
+fn apply_keystream(recipient: &mut [u8], tag: &[u8], key: &str) {
+    for (counter, chunk) in recipient.chunks_mut(32).enumerate() {
+        let mut mac = hmac(key, 1);
+        mac.update(tag);
+        mac.update(&(counter as u64).to_le_bytes());
+        let block = mac.finalize().into_bytes();
+        chunk.iter_mut().zip(block).for_each(|(b, k)| *b ^= k);
+    }
+}
+
+/// Finds the positions marks are inserted at, right after the punctuation ending each sentence
+/// and at the end of each line, before any trailing whitespace
+fn positions(document: &str) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let mut start = 0;
+    for line in document.split_inclusive('\n') {
+        let content = line.trim_end();
+        if !content.trim_start().is_empty() {
+            for (i, c) in content.char_indices() {
+                if matches!(c, '.' | '!' | '?') && content[i + 1..].starts_with(' ') {
+                    positions.push(start + i + 1);
+                }
+            }
+            positions.push(start + content.len());
+        }
+        start += line.len();
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fingerprint, identify};
+
+    const DOCUMENT: &str = include_str!("../samples/lorem.txt");
+    const RECIPIENTS: [&str; 3] = ["alice@example.com", "bob@example.com", "carol@example.com"];
+
+    #[test]
+    fn fingerprint_identify() {
+        let copies = fingerprint(DOCUMENT, &RECIPIENTS, "secret").unwrap();
+        assert_eq!(copies.len(), RECIPIENTS.len());
+        for (copy, recipient) in copies.iter().zip(RECIPIENTS.iter()) {
+            let visible: String = copy.chars().filter(|c| !crate::is_zw(*c)).collect();
+            assert_eq!(visible, DOCUMENT);
+            assert_eq!(identify(copy, "secret").as_deref(), Some(*recipient));
+            assert_eq!(identify(copy, "other"), None);
+
+            // the identifier can't be read from the mark without the key
+            let run = copy
+                .split(|c| !crate::is_zw(c))
+                .find(|r| !r.is_empty())
+                .unwrap();
+            let mark = crate::decode_to_vec(run).unwrap();
+            assert_eq!(mark.len(), super::TAG_LEN + recipient.len());
+            assert_ne!(&mark[super::TAG_LEN..], recipient.as_bytes());
+        }
+        assert_eq!(identify(DOCUMENT, "secret"), None);
+    }
+
+    #[test]
+    fn identify_excerpt() {
+        let copies = fingerprint(DOCUMENT, &RECIPIENTS, "secret").unwrap();
+        let copy = &copies[2];
+
+        // the last sentence of the document, starting halfway through the mark preceding it
+        let start = copy.find("Excepteur").unwrap() - 1;
+        let mark_len = copy[..start]
+            .chars()
+            .rev()
+            .take_while(|c| crate::is_zw(*c))
+            .count();
+        let excerpt = &copy[start - mark_len / 2 * 3..];
+        assert_eq!(identify(excerpt, "secret").as_deref(), Some(RECIPIENTS[2]));
+
+        // a mix of copies is attributed to the recipient whose copy contributed the most
+        let first_sentence = &copies[0][..copies[0].find("Ut enim").unwrap()];
+        let mixed = format!("{}{}", first_sentence, &copies[1]);
+        assert_eq!(identify(&mixed, "secret").as_deref(), Some(RECIPIENTS[1]));
+
+        assert!(matches!(
+            fingerprint("  \n", &RECIPIENTS, "secret"),
+            Err(super::Error::NoStrings)
+        ));
+    }
+}
@@ -0,0 +1,420 @@
+//! Collusion-resistant fingerprinting codes
+//!
+//! Uses the symmetric variant of Tardos codes. Every symbol of the code has a bias drawn from an arcsine distribution,
+//! and the symbols of each codeword are drawn using these biases. Both are derived from a secret key instead of being
+//! stored, so that only the key, the recipients and the original document are needed to accuse colluders.
+//!
+//! The code length is the asymptotic estimate `π²c²·ln(n/ε)` for `n` recipients and coalitions of `c` from
+//! Škorić, Katzenbeisser and Celik, "Symmetric Tardos fingerprinting codes for arbitrary alphabet sizes"
+//! (Designs, Codes and Cryptography, 2008), rather than one of their proven bounds, which are much longer.
+//! The accusation threshold `sqrt(2·ln(n/ε)·m)` for `m` recovered symbols treats the scores of innocent recipients as
+//! Gaussian. Their actual distribution has heavier tails, so the probability of a false accusation is a target which
+//! can be exceeded, not a guarantee.
+
+use super::Error;
+use core::f64::consts::{FRAC_PI_2, PI};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Targeted probability of falsely accusing at least one innocent recipient, under a Gaussian approximation
+const FALSE_ACCUSATION: f64 = 1e-3;
+
+/// Collusion-resistant fingerprinting code
+#[derive(Debug, Clone)]
+pub struct Tardos {
+    mac: Hmac<Sha256>,
+    len: usize,
+    cutoff: f64,
+    threshold: f64,
+}
+
+/// Recipient accused of having contributed to a leaked copy
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accused {
+    pub recipient: String,
+    /// Accusation score, higher scores meaning stronger evidence
+    pub score: f64,
+}
+
+impl Tardos {
+    /// Creates a code identifying at least one member of any coalition of up to `colluders` recipients
+    /// out of `recipients`, using a secret key
+    pub fn new(key: &str, recipients: usize, colluders: usize) -> Self {
+        let c = colluders.max(1) as f64;
+        let log = (recipients.max(1) as f64 / FALSE_ACCUSATION).ln();
+        Self {
+            mac: Hmac::new_from_slice(key.as_bytes()).expect("HMAC can take a key of any size"),
+            len: (PI * PI * c * c * log).ceil() as usize,
+            cutoff: 1.0 / (300.0 * c),
+            threshold: (2.0 * log).sqrt(),
+        }
+    }
+
+    /// Returns the number of symbols of a codeword
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if codewords have no symbols
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Produces one copy of a document per recipient, each carrying the codeword of its recipient
+    ///
+    /// The codeword is split in chunks of equal length inserted at the end of every word,
+    /// each symbol being written as a single zero-width character. Colluders can remove every chunk which differs
+    /// between their copies, so documents should have more words than the code has symbols.
+    pub fn fingerprint<S: AsRef<str>>(
+        &self,
+        document: &str,
+        recipients: &[S],
+    ) -> Result<Vec<String>, Error> {
+        let positions = word_ends(document);
+        if positions.is_empty() {
+            return Err(Error::NoStrings);
+        }
+        let chunk = self.chunk_len(positions.len());
+        let biases: Vec<f64> = (0..self.len).map(|i| self.bias(i)).collect();
+
+        Ok(recipients
+            .iter()
+            .map(|r| {
+                let codeword: Vec<bool> = (biases.iter().enumerate())
+                    .map(|(i, p)| self.symbol(r.as_ref(), i, *p))
+                    .collect();
+                let mut copy = String::with_capacity(document.len() + codeword.len() * 3);
+                let mut cursor = 0;
+                for (position, symbols) in positions.iter().zip(codeword.chunks(chunk)) {
+                    copy.push_str(&document[cursor..*position]);
+                    copy.extend(symbols.iter().map(|s| crate::CHARS[*s as usize]));
+                    cursor = *position;
+                }
+                copy.push_str(&document[cursor..]);
+                copy
+            })
+            .collect())
+    }
+
+    /// Accuses the recipients whose codeword matches the one found in a leaked copy well enough,
+    /// strongest evidence first
+    ///
+    /// The leaked copy can be an excerpt of the document, and can be merged from several copies.
+    /// It is aligned with the original document word by word, so changes to whitespace like reflowed lines
+    /// are ignored, and its first and last words can be cut. Other edits to its visible text make the alignment fail,
+    /// in which case no recipient is accused. Excerpts of repeated passages match several places of the document,
+    /// and the one giving the highest score is used, which makes a false accusation more likely by up to as many times.
+    /// Chunks which were removed or damaged are ignored.
+    pub fn accuse<S: AsRef<str>>(
+        &self,
+        original: &str,
+        leaked: &str,
+        recipients: &[S],
+    ) -> Vec<Accused> {
+        let mut best: Option<(Vec<Accused>, f64)> = None;
+        for symbols in self.recover(original, leaked) {
+            let scores = self.score(&symbols, recipients);
+            let top = |s: &[Accused]| s.first().map_or(f64::MIN, |a| a.score);
+            if best.as_ref().is_none_or(|(b, _)| top(&scores) > top(b)) {
+                let threshold = self.threshold * (symbols.len() as f64).sqrt();
+                best = Some((scores, threshold));
+            }
+        }
+
+        let (mut accused, threshold) = match best {
+            Some(best) => best,
+            None => return Vec::new(),
+        };
+        accused.retain(|a| a.score > threshold);
+        accused
+    }
+
+    /// Scores every recipient against symbols recovered from a leaked copy, highest score first
+    fn score<S: AsRef<str>>(&self, symbols: &[(usize, bool)], recipients: &[S]) -> Vec<Accused> {
+        let biases: Vec<f64> = symbols.iter().map(|(i, _)| self.bias(*i)).collect();
+        let mut scores: Vec<Accused> = recipients
+            .iter()
+            .map(|r| {
+                let recipient = r.as_ref();
+                let score = symbols
+                    .iter()
+                    .zip(&biases)
+                    .map(|((i, y), p)| {
+                        let x = self.symbol(recipient, *i, *p);
+                        let (p, q) = if *y { (*p, 1.0 - p) } else { (1.0 - p, *p) };
+                        if x == *y {
+                            (q / p).sqrt()
+                        } else {
+                            -(p / q).sqrt()
+                        }
+                    })
+                    .sum();
+                Accused {
+                    recipient: recipient.to_string(),
+                    score,
+                }
+            })
+            .collect();
+        scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+        scores
+    }
+
+    /// Returns the number of symbols inserted at each position of a document
+    fn chunk_len(&self, positions: usize) -> usize {
+        self.len.div_ceil(positions).max(1)
+    }
+
+    /// Finds the symbols of a leaked copy along with their index in the codeword,
+    /// once for every place of the original document the leaked copy matches
+    fn recover(&self, original: &str, leaked: &str) -> Vec<Vec<(usize, bool)>> {
+        let original = words(original);
+        let leaked = words(leaked);
+        let (first, last) = match (leaked.first(), leaked.last()) {
+            (Some((first, _)), Some((last, _))) => (first, last),
+            _ => return Vec::new(),
+        };
+        let chunk = self.chunk_len(original.len());
+
+        (0..(original.len() + 1).saturating_sub(leaked.len()))
+            .filter(|k| {
+                let window = &original[*k..*k + leaked.len()];
+                match window {
+                    [(only, _)] => only.contains(first.as_str()),
+                    [(start, _), middle @ .., (end, _)] => {
+                        start.ends_with(first.as_str())
+                            && end.starts_with(last.as_str())
+                            && middle
+                                .iter()
+                                .zip(&leaked[1..])
+                                .all(|((o, _), (l, _))| o == l)
+                    }
+                    [] => false,
+                }
+            })
+            .map(|k| {
+                let mut symbols = Vec::new();
+                for (j, (_, run)) in leaked.iter().enumerate() {
+                    let indices = (k + j) * chunk..((k + j + 1) * chunk).min(self.len);
+                    if run.chars().count() != indices.len() {
+                        continue;
+                    }
+                    for (i, c) in indices.zip(run.chars()) {
+                        match c {
+                            c if c == crate::CHARS[0] => symbols.push((i, false)),
+                            c if c == crate::CHARS[1] => symbols.push((i, true)),
+                            _ => (),
+                        }
+                    }
+                }
+                symbols
+            })
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    /// Derives the symbol of the codeword of a recipient at an index
+    fn symbol(&self, recipient: &str, index: usize, bias: f64) -> bool {
+        self.uniform(b"symbol", index, recipient.as_bytes()) < bias
+    }
+
+    /// Derives the bias of the symbols at an index, drawn from an arcsine distribution within the cutoff
+    fn bias(&self, index: usize) -> f64 {
+        let low = self.cutoff.sqrt().asin();
+        let r = low + (FRAC_PI_2 - 2.0 * low) * self.uniform(b"bias", index, b"");
+        r.sin().powi(2)
+    }
+
+    /// Derives a uniformly distributed value in `[0, 1)` from the key
+    fn uniform(&self, domain: &[u8], index: usize, data: &[u8]) -> f64 {
+        let mut mac = self.mac.clone();
+        mac.update(domain);
+        mac.update(&(index as u64).to_le_bytes());
+        mac.update(data);
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&mac.finalize().into_bytes()[..8]);
+        (u64::from_le_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Splits a document into its words, without their zero-width characters,
+/// along with the zero-width characters found right after each of them
+///
+/// Zero-width characters which aren't at the end of a word are ignored.
+fn words(document: &str) -> Vec<(String, String)> {
+    let mut words: Vec<(String, String)> = Vec::new();
+    let mut in_word = false;
+    for c in document.chars() {
+        if c.is_whitespace() {
+            in_word = false;
+        } else if crate::is_zw(c) {
+            if let Some((_, run)) = words.last_mut().filter(|_| in_word) {
+                run.push(c);
+            }
+        } else {
+            match words.last_mut().filter(|_| in_word) {
+                Some((word, run)) => {
+                    word.push(c);
+                    run.clear();
+                }
+                None => words.push((c.to_string(), String::new())),
+            }
+            in_word = true;
+        }
+    }
+    words
+}
+
+/// Finds the positions right after the end of each word of a document
+fn word_ends(document: &str) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let mut in_word = false;
+    for (i, c) in document.char_indices() {
+        let word = !c.is_whitespace() && !crate::is_zw(c);
+        if in_word && !word {
+            positions.push(i);
+        }
+        in_word = word;
+    }
+    if in_word {
+        positions.push(document.len());
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tardos;
+
+    const DOCUMENT: &str = include_str!("../../samples/lorem.txt");
+
+    fn recipients() -> Vec<String> {
+        (0..20).map(|i| format!("recipient-{}", i)).collect()
+    }
+
+    /// Merges copies chunk by chunk, choosing the symbols of each chunk using the provided strategy
+    fn merge<F: FnMut(usize, &[&str]) -> String>(copies: &[&String], mut choose: F) -> String {
+        let split = |copy: &str| -> Vec<String> {
+            let mut parts = vec![String::new()];
+            for c in copy.chars() {
+                let zw = crate::is_zw(c);
+                if zw
+                    != parts
+                        .last()
+                        .unwrap()
+                        .chars()
+                        .next()
+                        .map_or(zw, crate::is_zw)
+                {
+                    parts.push(String::new());
+                }
+                parts.last_mut().unwrap().push(c);
+            }
+            parts
+        };
+        let parts: Vec<Vec<String>> = copies.iter().map(|c| split(c)).collect();
+
+        let mut merged = String::new();
+        for i in 0..parts[0].len() {
+            let options: Vec<&str> = parts.iter().map(|p| p[i].as_str()).collect();
+            if options.iter().all(|o| *o == options[0]) {
+                merged.push_str(options[0]);
+            } else {
+                merged.push_str(&choose(i, &options));
+            }
+        }
+        merged
+    }
+
+    #[test]
+    fn tardos_single() {
+        let recipients = recipients();
+        let code = Tardos::new("secret", recipients.len(), 3);
+        let copies = code.fingerprint(DOCUMENT, &recipients).unwrap();
+
+        for (copy, recipient) in copies.iter().zip(&recipients) {
+            let visible: String = copy.chars().filter(|c| !crate::is_zw(*c)).collect();
+            assert_eq!(visible, DOCUMENT);
+
+            let accused = code.accuse(DOCUMENT, copy, &recipients);
+            assert_eq!(accused.len(), 1);
+            assert_eq!(&accused[0].recipient, recipient);
+        }
+
+        let other = Tardos::new("other", recipients.len(), 3);
+        assert!(other.accuse(DOCUMENT, &copies[0], &recipients).is_empty());
+        assert!(code.accuse(DOCUMENT, DOCUMENT, &recipients).is_empty());
+        assert!(code.accuse("unrelated", &copies[0], &recipients).is_empty());
+        assert!(code.fingerprint(" \n", &recipients).is_err());
+    }
+
+    #[test]
+    fn tardos_collusion() {
+        // long enough for every word to carry a single symbol
+        let document = DOCUMENT.repeat(16);
+        let recipients = recipients();
+        let code = Tardos::new("secret", recipients.len(), 3);
+        let copies = code.fingerprint(&document, &recipients).unwrap();
+        assert_eq!(code.chunk_len(super::word_ends(&document).len()), 1);
+
+        for colluders in [[0, 1, 2], [4, 11, 19]].iter() {
+            let coalition: Vec<&String> = colluders.iter().map(|i| &copies[*i]).collect();
+            let symbol_count = |s: &str, c: char| s.chars().filter(|x| *x == c).count();
+            let merged = [
+                // interleaving the chunks of every copy
+                merge(&coalition, |i, o| o[i % o.len()].to_string()),
+                // removing every chunk which differs
+                merge(&coalition, |_, _| String::new()),
+                // the chunk of the copy with the fewest and the most ones
+                merge(&coalition, |_, o| {
+                    let min = o.iter().min_by_key(|s| symbol_count(s, crate::CHARS[1]));
+                    min.unwrap().to_string()
+                }),
+                merge(&coalition, |_, o| {
+                    let max = o.iter().max_by_key(|s| symbol_count(s, crate::CHARS[1]));
+                    max.unwrap().to_string()
+                }),
+            ];
+
+            for merged in merged.iter() {
+                let accused = code.accuse(&document, merged, &recipients);
+                assert!(!accused.is_empty());
+                assert!(accused
+                    .iter()
+                    .all(|a| colluders.iter().any(|i| recipients[*i] == a.recipient)));
+            }
+        }
+    }
+
+    #[test]
+    fn tardos_excerpt() {
+        let recipients = recipients();
+        let code = Tardos::new("secret", recipients.len(), 2);
+        let copies = code.fingerprint(DOCUMENT, &recipients).unwrap();
+
+        let copy = &copies[6];
+        let start = copy.find("Duis").unwrap();
+        let accused = code.accuse(DOCUMENT, &copy[start..], &recipients);
+        assert_eq!(accused[0].recipient, recipients[6]);
+
+        // starting in the middle of a word, with lines reflowed and spaces collapsed
+        let excerpt = copy[start + 2..].replace('\n', " ").replace("  ", " ");
+        let accused = code.accuse(DOCUMENT, &excerpt, &recipients);
+        assert_eq!(accused[0].recipient, recipients[6]);
+    }
+
+    #[test]
+    fn tardos_repeated_excerpt() {
+        let document = DOCUMENT.repeat(3);
+        let recipients = recipients();
+        let code = Tardos::new("secret", recipients.len(), 2);
+        let copies = code.fingerprint(&document, &recipients).unwrap();
+
+        // the last repetition matches three places of the document, only one of which carries its symbols
+        let copy = &copies[9];
+        let start = copy.rfind("Lorem").unwrap();
+        let accused = code.accuse(&document, &copy[start..], &recipients);
+        assert_eq!(accused.len(), 1);
+        assert_eq!(accused[0].recipient, recipients[9]);
+        assert_eq!(code.recover(&document, &copy[start..]).len(), 3);
+    }
+}
@@ -0,0 +1,693 @@
+//! Hidden payloads inside structured documents
+//!
+//! The payload is concealed the same way [`camouflage`](crate::camouflage) does it, then split across the string values
+//! of the document in traversal order. Keys and other values are left untouched. JSON documents are serialized again,
+//! keeping their indentation, while TOML and YAML documents are modified in place so that their layout is kept.
+//! Markup documents are not parsed into a tree, only their visible text is modified.
+
+#[cfg(any(
+    feature = "email",
+    feature = "html",
+    feature = "json",
+    feature = "markdown",
+    feature = "source",
+    feature = "toml",
+    feature = "yaml"
+))]
+use crate::camo::{conceal, reveal};
+use core::fmt;
+
+#[cfg(feature = "office")]
+mod container;
+#[cfg(feature = "email")]
+mod email;
+#[cfg(feature = "html")]
+mod html;
+#[cfg(feature = "markdown")]
+mod markdown;
+#[cfg(feature = "source")]
+mod source;
+
+#[cfg(feature = "office")]
+pub use container::{embed_container, extract_container, locate_container, Container, Found};
+#[cfg(feature = "source")]
+pub use source::Language;
+
+/// Represents an error that might occur while hiding or retrieving a payload inside a document
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    Camo(crate::camo::Error),
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    #[cfg(feature = "toml")]
+    Toml(toml_edit::TomlError),
+    #[cfg(feature = "yaml")]
+    Yaml(saphyr_parser::ScanError),
+    #[cfg(feature = "office")]
+    Zip(zip::result::ZipError),
+    NoStrings,
+    ZeroWidthText,
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Camo(e) => write!(f, "{}", e),
+            #[cfg(feature = "json")]
+            Self::Json(e) => write!(f, "JSON error: {}", e),
+            #[cfg(feature = "toml")]
+            Self::Toml(e) => write!(f, "TOML error: {}", e),
+            #[cfg(feature = "yaml")]
+            Self::Yaml(e) => write!(f, "YAML error: {}", e),
+            #[cfg(feature = "office")]
+            Self::Zip(e) => write!(f, "zip error: {}", e),
+            Self::NoStrings => write!(f, "no text in document to hide data in"),
+            Self::ZeroWidthText => write!(
+                f,
+                "the document already contains zero-width characters where data would be hidden"
+            ),
+        }
+    }
+}
+impl std::error::Error for Error {}
+impl From<crate::camo::Error> for Error {
+    fn from(e: crate::camo::Error) -> Self {
+        Self::Camo(e)
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::Camo(crate::camo::Error::Io(e))
+    }
+}
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+#[cfg(feature = "toml")]
+impl From<toml_edit::TomlError> for Error {
+    fn from(e: toml_edit::TomlError) -> Self {
+        Self::Toml(e)
+    }
+}
+#[cfg(feature = "yaml")]
+impl From<saphyr_parser::ScanError> for Error {
+    fn from(e: saphyr_parser::ScanError) -> Self {
+        Self::Yaml(e)
+    }
+}
+#[cfg(feature = "office")]
+impl From<zip::result::ZipError> for Error {
+    fn from(e: zip::result::ZipError) -> Self {
+        Self::Zip(e)
+    }
+}
+
+/// Structured document format
+#[cfg(any(
+    feature = "email",
+    feature = "html",
+    feature = "json",
+    feature = "markdown",
+    feature = "source",
+    feature = "toml",
+    feature = "yaml"
+))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Format {
+    /// RFC 5322 email message, where every text part carries the payload
+    #[cfg(feature = "email")]
+    Email,
+    /// HTML document, where only visible text nodes are used
+    ///
+    /// Zero-width characters are written as character references like `&zwnj;` if `entities` is set.
+    #[cfg(feature = "html")]
+    Html { entities: bool },
+    /// JSON document
+    #[cfg(feature = "json")]
+    Json,
+    /// Markdown document, where only prose text is used
+    #[cfg(feature = "markdown")]
+    Markdown,
+    /// Source file, where only comments are used
+    #[cfg(feature = "source")]
+    Source(Language),
+    /// TOML document, where string values are modified in place so that its layout and comments are kept
+    #[cfg(feature = "toml")]
+    Toml,
+    /// YAML document, where string scalars are modified in place so that its layout, comments and anchors are kept
+    ///
+    /// Aliases are not followed, so a string is only used where it is anchored.
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+/// Hides a compressed and optionally encrypted payload across the string values or text of a document
+///
+/// Fails with [`Error::ZeroWidthText`] if the text the payload would be hidden in already contains zero-width characters,
+/// like the joiners of emoji sequences, since they would be read back along with the payload.
+#[cfg(any(
+    feature = "email",
+    feature = "html",
+    feature = "json",
+    feature = "markdown",
+    feature = "source",
+    feature = "toml",
+    feature = "yaml"
+))]
+pub fn embed(
+    document: &str,
+    format: Format,
+    payload: &[u8],
+    key: Option<&str>,
+    compression_level: Option<i32>,
+) -> Result<String, Error> {
+    if !collect(document, format)?.is_empty() {
+        return Err(Error::ZeroWidthText);
+    }
+    let concealed = conceal(payload, key, compression_level)?;
+
+    let mut embedded = match format {
+        #[cfg(feature = "email")]
+        Format::Email => email::embed(document, &concealed)?,
+        #[cfg(feature = "html")]
+        Format::Html { entities } => html::embed(document, &concealed, entities)?,
+        #[cfg(feature = "markdown")]
+        Format::Markdown => markdown::embed(document, &concealed)?,
+        #[cfg(feature = "source")]
+        Format::Source(language) => source::embed(document, &concealed, language)?,
+        #[cfg(feature = "json")]
+        Format::Json => {
+            let mut value: serde_json::Value = serde_json::from_str(document)?;
+            let mut strings = Vec::new();
+            json_strings(&mut value, &mut strings);
+            distribute(&concealed, strings)?;
+            json_to_string(&value, document)?
+        }
+        #[cfg(feature = "toml")]
+        Format::Toml => {
+            let positions: Vec<usize> = toml_strings(document)?
+                .into_iter()
+                .map(|(p, _)| p)
+                .collect();
+            insert(document, &positions, &concealed, String::push_str)?
+        }
+        #[cfg(feature = "yaml")]
+        Format::Yaml => {
+            let positions: Vec<usize> = yaml_strings(document)?
+                .into_iter()
+                .map(|(p, _)| p)
+                .collect();
+            insert(document, &positions, &concealed, String::push_str)?
+        }
+    };
+
+    if document.ends_with('\n') && !embedded.ends_with('\n') {
+        embedded.push('\n');
+    } else if !document.ends_with('\n') && embedded.ends_with('\n') {
+        embedded.pop();
+    }
+    Ok(embedded)
+}
+
+/// Retrieves a compressed and optionally encrypted payload hidden using [`embed`] from a document
+#[cfg(any(
+    feature = "email",
+    feature = "html",
+    feature = "json",
+    feature = "markdown",
+    feature = "source",
+    feature = "toml",
+    feature = "yaml"
+))]
+pub fn extract(document: &str, format: Format, key: Option<&str>) -> Result<Vec<u8>, Error> {
+    #[cfg(feature = "email")]
+    if format == Format::Email {
+        return email::extract(document, key);
+    }
+    Ok(reveal(&collect(document, format)?, key)?)
+}
+
+/// Collects the zero-width characters of the string values or text of a document
+/// which a payload would be hidden in, in traversal order
+///
+/// Email messages are handled part by part, so nothing is collected from them.
+#[cfg(any(
+    feature = "email",
+    feature = "html",
+    feature = "json",
+    feature = "markdown",
+    feature = "source",
+    feature = "toml",
+    feature = "yaml"
+))]
+fn collect(document: &str, format: Format) -> Result<String, Error> {
+    Ok(match format {
+        #[cfg(feature = "email")]
+        Format::Email => String::new(),
+        #[cfg(feature = "html")]
+        Format::Html { .. } => html::extract(document),
+        #[cfg(feature = "markdown")]
+        Format::Markdown => markdown::extract(document),
+        #[cfg(feature = "source")]
+        Format::Source(language) => source::extract(document, language),
+        #[cfg(feature = "json")]
+        Format::Json => {
+            let mut value: serde_json::Value = serde_json::from_str(document)?;
+            let mut strings = Vec::new();
+            json_strings(&mut value, &mut strings);
+            zero_width(strings.into_iter().map(|s| s.as_str()))
+        }
+        #[cfg(feature = "toml")]
+        Format::Toml => zero_width(toml_strings(document)?.iter().map(|(_, s)| s.as_str())),
+        #[cfg(feature = "yaml")]
+        Format::Yaml => zero_width(yaml_strings(document)?.iter().map(|(_, s)| s.as_str())),
+    })
+}
+
+/// Collects the zero-width characters of strings
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+fn zero_width<'a, I: Iterator<Item = &'a str>>(strings: I) -> String {
+    strings
+        .flat_map(|s| s.chars())
+        .filter(|c| crate::is_zw(*c))
+        .collect()
+}
+
+/// Appends consecutive parts of a concealed payload to each string
+#[cfg(feature = "json")]
+fn distribute(concealed: &str, mut strings: Vec<&mut String>) -> Result<(), Error> {
+    if strings.is_empty() {
+        return Err(Error::NoStrings);
+    }
+
+    let parts = split_concealed(concealed, strings.len());
+    for (string, part) in strings.iter_mut().zip(parts) {
+        string.push_str(part);
+    }
+    Ok(())
+}
+
+/// Inserts consecutive parts of a concealed payload at each position of a document,
+/// writing them using the provided function
+#[cfg(any(
+    feature = "html",
+    feature = "markdown",
+    feature = "source",
+    feature = "toml",
+    feature = "yaml"
+))]
+fn insert<F: FnMut(&mut String, &str)>(
+    document: &str,
+    positions: &[usize],
+    concealed: &str,
+    mut write: F,
+) -> Result<String, Error> {
+    if positions.is_empty() {
+        return Err(Error::NoStrings);
+    }
+
+    let mut embedded = String::with_capacity(document.len() + concealed.len() * 3);
+    let mut cursor = 0;
+    for (position, part) in positions
+        .iter()
+        .zip(split_concealed(concealed, positions.len()))
+    {
+        embedded.push_str(&document[cursor..*position]);
+        write(&mut embedded, part);
+        cursor = *position;
+    }
+    embedded.push_str(&document[cursor..]);
+    Ok(embedded)
+}
+
+/// Splits a concealed payload into a number of contiguous parts of nearly equal length
+fn split_concealed(concealed: &str, parts: usize) -> Vec<&str> {
+    // every zero-width character is encoded using 3 bytes
+    let len = concealed.len() / 3;
+    (0..parts)
+        .map(|i| &concealed[(i * len / parts) * 3..((i + 1) * len / parts) * 3])
+        .collect()
+}
+
+/// Collects the string values of a JSON document in traversal order
+#[cfg(feature = "json")]
+fn json_strings<'a>(value: &'a mut serde_json::Value, strings: &mut Vec<&'a mut String>) {
+    use serde_json::Value;
+
+    match value {
+        Value::String(s) => strings.push(s),
+        Value::Array(a) => a.iter_mut().for_each(|v| json_strings(v, strings)),
+        Value::Object(o) => o.values_mut().for_each(|v| json_strings(v, strings)),
+        _ => (),
+    }
+}
+
+/// Serializes a JSON document, using the indentation of the original document if it was pretty printed
+#[cfg(feature = "json")]
+fn json_to_string(value: &serde_json::Value, original: &str) -> Result<String, Error> {
+    use ::serde::Serialize;
+    use serde_json::ser::{PrettyFormatter, Serializer};
+
+    let indent = original
+        .lines()
+        .map(|l| &l[..l.len() - l.trim_start().len()])
+        .find(|i| !i.is_empty());
+    let indent = match indent {
+        Some(i) => i,
+        None => return Ok(serde_json::to_string(value)?),
+    };
+
+    let mut out = Vec::with_capacity(original.len());
+    let mut serializer =
+        Serializer::with_formatter(&mut out, PrettyFormatter::with_indent(indent.as_bytes()));
+    value.serialize(&mut serializer)?;
+    // the serializer only ever writes valid UTF-8
+    Ok(String::from_utf8(out).unwrap())
+}
+
+/// Finds the string values of a TOML document in the order they appear in,
+/// returning the position right before the closing quote of each along with its value
+#[cfg(feature = "toml")]
+fn toml_strings(document: &str) -> Result<Vec<(usize, String)>, Error> {
+    let parsed = toml_edit::Document::parse(document)?;
+    let mut strings = Vec::new();
+    toml_item_strings(document, parsed.as_item(), &mut strings);
+    // tables can be defined out of order
+    strings.sort_by_key(|(p, _)| *p);
+    Ok(strings)
+}
+
+/// Collects the string values of a TOML item along with their position, see [`toml_strings`]
+#[cfg(feature = "toml")]
+fn toml_item_strings(document: &str, item: &toml_edit::Item, strings: &mut Vec<(usize, String)>) {
+    use toml_edit::Item;
+
+    match item {
+        Item::Value(v) => toml_value_strings(document, v, strings),
+        Item::Table(t) => t
+            .iter()
+            .for_each(|(_, i)| toml_item_strings(document, i, strings)),
+        Item::ArrayOfTables(a) => a
+            .iter()
+            .flat_map(|t| t.iter())
+            .for_each(|(_, i)| toml_item_strings(document, i, strings)),
+        Item::None => (),
+    }
+}
+
+/// Collects the string values of a TOML value along with their position, see [`toml_strings`]
+#[cfg(feature = "toml")]
+fn toml_value_strings(
+    document: &str,
+    value: &toml_edit::Value,
+    strings: &mut Vec<(usize, String)>,
+) {
+    use toml_edit::Value;
+
+    match value {
+        Value::String(s) => {
+            if let Some(span) = s.span() {
+                let raw = &document[span.clone()];
+                let multiline = raw.starts_with("\"\"\"") || raw.starts_with("'''");
+                strings.push((span.end - if multiline { 3 } else { 1 }, s.value().clone()));
+            }
+        }
+        Value::Array(a) => a
+            .iter()
+            .for_each(|v| toml_value_strings(document, v, strings)),
+        Value::InlineTable(t) => t
+            .iter()
+            .for_each(|(_, v)| toml_value_strings(document, v, strings)),
+        _ => (),
+    }
+}
+
+/// Finds the string scalars of a YAML document which aren't mapping keys, in the order they appear in,
+/// returning the position right after the last character of each, or before its closing quote, along with its value
+#[cfg(feature = "yaml")]
+fn yaml_strings(document: &str) -> Result<Vec<(usize, String)>, Error> {
+    use saphyr_parser::{Event, Parser, ScalarStyle};
+
+    /// Collection being parsed
+    struct Collection {
+        mapping: bool,
+        /// Whether the next node is a mapping key
+        key: bool,
+        /// Whether the collection is itself part of a mapping key
+        in_key: bool,
+    }
+
+    // markers count characters, not bytes
+    let offsets: Vec<usize> = document
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(Some(document.len()))
+        .collect();
+    let mut stack: Vec<Collection> = Vec::new();
+    let mut strings = Vec::new();
+    for event in Parser::new_from_str(document) {
+        let (event, span) = event?;
+        let in_key = stack
+            .last()
+            .is_some_and(|c| c.in_key || (c.mapping && c.key));
+        let node = match event {
+            Event::Scalar(value, style, _, tag) => {
+                let string = match (&tag, style) {
+                    (Some(t), _) if t.is_yaml_core_schema() => t.suffix == "str",
+                    (_, ScalarStyle::Plain) => !is_plain_yaml_value(&value),
+                    _ => true,
+                };
+                if string && !in_key {
+                    let start = offsets[span.start.index()];
+                    let end = offsets[span.end.index()];
+                    let position = match style {
+                        ScalarStyle::Plain => end,
+                        ScalarStyle::SingleQuoted => closing_quote(document, start, '\''),
+                        ScalarStyle::DoubleQuoted => closing_quote(document, start, '"'),
+                        ScalarStyle::Literal | ScalarStyle::Folded => {
+                            start + document[start..end].trim_end().len()
+                        }
+                    };
+                    strings.push((position, value.into_owned()));
+                }
+                true
+            }
+            Event::Alias(_) => true,
+            Event::SequenceStart(..) | Event::MappingStart(..) => {
+                stack.push(Collection {
+                    mapping: matches!(event, Event::MappingStart(..)),
+                    key: true,
+                    in_key,
+                });
+                false
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                stack.pop();
+                true
+            }
+            _ => false,
+        };
+        if let Some(c) = stack.last_mut().filter(|c| node && c.mapping) {
+            c.key = !c.key;
+        }
+    }
+    Ok(strings)
+}
+
+/// Returns whether a plain YAML scalar is a null, boolean or number according to the core schema
+#[cfg(feature = "yaml")]
+fn is_plain_yaml_value(value: &str) -> bool {
+    const KEYWORDS: [&str; 22] = [
+        "", "~", "null", "Null", "NULL", "true", "True", "TRUE", "false", "False", "FALSE", ".inf",
+        ".Inf", ".INF", "+.inf", "+.Inf", "+.INF", "-.inf", "-.Inf", "-.INF", ".nan", ".NaN",
+    ];
+    let radix = |prefix: &str, radix: u32| {
+        value
+            .strip_prefix(prefix)
+            .is_some_and(|v| !v.is_empty() && v.chars().all(|c| c.is_digit(radix)))
+    };
+    let number = value.bytes().any(|b| b.is_ascii_digit())
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.' | b'e' | b'E'))
+        && value.parse::<f64>().is_ok();
+    KEYWORDS.contains(&value) || value == ".NAN" || radix("0o", 8) || radix("0x", 16) || number
+}
+
+/// Returns the position of the closing quote of a quoted YAML scalar starting at an index
+#[cfg(feature = "yaml")]
+fn closing_quote(document: &str, start: usize, quote: char) -> usize {
+    let mut chars = document[start + 1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            // quotes are escaped by doubling them in single quoted scalars
+            '\'' if quote == '\'' && document[start + 1 + i + 1..].starts_with('\'') => {
+                chars.next();
+            }
+            c if c == quote => return start + 1 + i,
+            _ => (),
+        }
+    }
+    document.len()
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(any(
+        feature = "email",
+        feature = "html",
+        feature = "json",
+        feature = "markdown",
+        feature = "source",
+        feature = "toml",
+        feature = "yaml"
+    ))]
+    use super::{embed, extract, Format};
+
+    #[cfg(any(
+        feature = "email",
+        feature = "html",
+        feature = "json",
+        feature = "markdown",
+        feature = "source",
+        feature = "toml",
+        feature = "yaml"
+    ))]
+    const SRC: &[u8] = include_bytes!("../samples/lorem.txt");
+
+    #[cfg(any(
+        feature = "email",
+        feature = "html",
+        feature = "json",
+        feature = "markdown",
+        feature = "source",
+        feature = "toml",
+        feature = "yaml"
+    ))]
+    pub(super) fn assert_round_trip(document: &str, format: Format) -> String {
+        let embedded = embed(document, format, SRC, Some("secret"), None).unwrap();
+        let visible: String = embedded.chars().filter(|c| !crate::is_zw(*c)).collect();
+        assert_ne!(embedded, visible);
+        assert_eq!(
+            SRC,
+            extract(&embedded, format, Some("secret"))
+                .unwrap()
+                .as_slice()
+        );
+        visible
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        let document = include_str!("../samples/schema.json");
+        let visible = assert_round_trip(document, Format::Json);
+
+        let original: serde_json::Value = serde_json::from_str(document).unwrap();
+        let stripped: serde_json::Value = serde_json::from_str(&visible).unwrap();
+        assert_eq!(original, stripped);
+        assert!(visible.starts_with("{\n    \"$schema\""));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_round_trip() {
+        let document = "# Example\ntitle = \"Example\" # inline comment\ntags = ['a', \"b\"]\n\n[owner]\nname = { first = \"Some\", last = '''one''' }\nbio = \"\"\"\nMultiple\nlines\"\"\"\nage = 42\n\n[[pets]]\n\"quoted key\" = \"Rex\"\n";
+        let visible = assert_round_trip(document, Format::Toml);
+        assert_eq!(document, visible);
+
+        // every string carries part of the payload, even when tables are defined out of order
+        let document = "[a.b]\nc = \"d\"\n[e]\nf = \"g\"\n[a]\nh = \"i\"\n";
+        let embedded = embed(document, Format::Toml, SRC, None, None).unwrap();
+        for key in ["c", "f", "h"].iter() {
+            let line = embedded.lines().find(|l| l.starts_with(key)).unwrap();
+            assert!(line.ends_with('"') && line.chars().any(crate::is_zw));
+        }
+        assert_eq!(
+            SRC,
+            extract(&embedded, Format::Toml, None).unwrap().as_slice()
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_round_trip() {
+        let document = "# Example\ntitle: Example  # inline comment\ntags:\n- 'a'\n- \"b\\\"\"\n- 42\n- true\nowner: &owner\n  name: Someone\n  bio: |\n    Multiple\n    lines\n\n  age: 42\ncopy: *owner\nflow: {x: 'it''s', y: [z]}\n";
+        let visible = assert_round_trip(document, Format::Yaml);
+        assert_eq!(document, visible);
+
+        // only strings carry the payload, and aliases are left alone
+        let embedded = embed(document, Format::Yaml, SRC, None, None).unwrap();
+        for unchanged in [
+            "title:",
+            "- 42\n",
+            "- true\n",
+            "age: 42\n",
+            "copy: *owner\n",
+        ]
+        .iter()
+        {
+            assert!(embedded.contains(unchanged));
+        }
+        let quoted = embedded.find("it''s").unwrap() + 5;
+        assert!(embedded[quoted..].starts_with(crate::is_zw));
+    }
+
+    #[test]
+    fn split_concealed() {
+        let concealed: String = crate::CHARS.iter().cycle().take(20).collect();
+        let parts = super::split_concealed(&concealed, 7);
+        assert_eq!(parts.len(), 7);
+        assert!(parts.iter().all(|p| (2..=3).contains(&p.chars().count())));
+        assert_eq!(parts.concat(), concealed);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn zero_width_carrier() {
+        // the family emoji is made of three emoji joined by U+200D
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+
+        let document = format!("{{\"greeting\": \"Hello from the {}\"}}", family);
+        assert!(matches!(
+            embed(&document, Format::Json, SRC, None, None),
+            Err(super::Error::ZeroWidthText)
+        ));
+
+        // keys are never used to hide data
+        let document = format!("{{\"{}\": \"Hello\"}}", family);
+        assert_round_trip(&document, Format::Json);
+    }
+
+    #[cfg(feature = "html")]
+    #[test]
+    fn zero_width_html() {
+        let document = "<p>Hello from the \u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}</p>";
+        assert!(matches!(
+            embed(document, Format::Html { entities: false }, SRC, None, None),
+            Err(super::Error::ZeroWidthText)
+        ));
+        let document = "<p>Hello from the &#x1F468;&zwj;&#x1F469;</p>";
+        assert!(matches!(
+            embed(document, Format::Html { entities: true }, SRC, None, None),
+            Err(super::Error::ZeroWidthText)
+        ));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn no_strings() {
+        assert!(matches!(
+            embed("[1, 2, 3]", Format::Json, SRC, None, None),
+            Err(super::Error::NoStrings)
+        ));
+        assert!(extract("{\"a\": \"b\"}", Format::Json, None).is_err());
+        assert!(extract("{", Format::Json, None).is_err());
+    }
+}
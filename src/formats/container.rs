@@ -0,0 +1,545 @@
+//! Binary documents stored in zip containers
+//!
+//! Only the text runs of the files holding the document content are modified,
+//! every other file of the container is copied untouched.
+//! Each content file carries the whole payload, so that chapters of a publication can be identified on their own.
+//!
+//! PDF documents are not supported since their text is drawn using font specific glyph encodings,
+//! which have no way of representing zero-width characters.
+
+use super::{html, insert, Error};
+use crate::camo::{conceal, reveal};
+use std::io::{Cursor, Read, Seek, Write};
+use std::ops::Range;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// Document stored in a zip container
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Container {
+    /// Office Open XML word processing document, as produced by Microsoft Word
+    Docx,
+    /// EPUB publication, where each XHTML chapter of the spine carries the payload
+    Epub,
+    /// OpenDocument text document, as produced by LibreOffice Writer
+    Odt,
+}
+impl Container {
+    /// Guesses the type of a document from its extension
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "docx" | "docm" => Some(Self::Docx),
+            "epub" => Some(Self::Epub),
+            "odt" | "ott" => Some(Self::Odt),
+            _ => None,
+        }
+    }
+
+    /// Returns the names of the files of a container which hold the document content, in reading order
+    fn files<R: Read + Seek>(self, archive: &mut ZipArchive<R>) -> Result<Vec<String>, Error> {
+        match self {
+            Self::Docx => Ok(vec!["word/document.xml".to_string()]),
+            Self::Epub => spine(archive),
+            Self::Odt => Ok(vec!["content.xml".to_string()]),
+        }
+    }
+
+    /// Finds the text runs of a content file which are part of the document body
+    fn text_runs(self, xml: &str) -> Vec<Range<usize>> {
+        match self {
+            Self::Docx => runs(xml, &["w:t"]),
+            Self::Epub => html::text_nodes(xml),
+            Self::Odt => runs(xml, &["text:a", "text:h", "text:p", "text:span"]),
+        }
+    }
+
+    /// Returns the text of each text run of a content file without its zero-width characters,
+    /// along with whether it had any
+    fn stripped_runs(self, xml: &str) -> Vec<(String, bool)> {
+        self.text_runs(xml)
+            .into_iter()
+            .map(|r| match self {
+                Self::Epub => html::strip(&xml[r]),
+                _ => {
+                    let text = &xml[r];
+                    let stripped: String = text.chars().filter(|c| !crate::is_zw(*c)).collect();
+                    let found = stripped.len() != text.len();
+                    (stripped, found)
+                }
+            })
+            .collect()
+    }
+
+    /// Collects the zero-width characters of the text runs of a content file
+    fn concealed(self, xml: &str) -> String {
+        match self {
+            Self::Epub => html::extract(xml),
+            _ => self
+                .text_runs(xml)
+                .into_iter()
+                .flat_map(|r| xml[r].chars())
+                .filter(|c| crate::is_zw(*c))
+                .collect(),
+        }
+    }
+}
+
+/// Payload found in one of the content files of a container, along with where it was found
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Found {
+    /// Name of the file inside of the container, such as an EPUB chapter
+    pub file: String,
+    /// Text of the first text run of the file carrying part of the payload, as written in the file
+    pub first: String,
+    /// Text of the last text run of the file carrying part of the payload, as written in the file
+    pub last: String,
+    pub payload: Vec<u8>,
+}
+
+/// Hides a compressed and optionally encrypted payload across the text runs of each content file of a document
+///
+/// Fails with [`Error::ZeroWidthText`] if a text run already contains zero-width characters.
+pub fn embed_container(
+    document: &[u8],
+    container: Container,
+    payload: &[u8],
+    key: Option<&str>,
+    compression_level: Option<i32>,
+) -> Result<Vec<u8>, Error> {
+    let concealed = conceal(payload, key, compression_level)?;
+    let mut archive = ZipArchive::new(Cursor::new(document))?;
+
+    let mut files = Vec::new();
+    for name in container.files(&mut archive)? {
+        let xml = read_file(&mut archive, &name)?;
+        if !container.concealed(&xml).is_empty() {
+            return Err(Error::ZeroWidthText);
+        }
+        // insert at the end of the run content, before any trailing whitespace
+        let positions: Vec<usize> = container
+            .text_runs(&xml)
+            .into_iter()
+            .map(|r| r.start + xml[r].trim_end().len())
+            .collect();
+        if !positions.is_empty() {
+            let xml = insert(&xml, &positions, &concealed, String::push_str)?;
+            files.push((name, xml));
+        }
+    }
+    if files.is_empty() {
+        return Err(Error::NoStrings);
+    }
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::with_capacity(document.len())));
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        let name = file.name()?.into_owned();
+        match files.iter().find(|(n, _)| *n == name) {
+            Some((_, xml)) => {
+                let mut options =
+                    SimpleFileOptions::default().compression_method(file.compression());
+                if let Some(time) = file.last_modified() {
+                    options = options.last_modified_time(time);
+                }
+                writer.start_file(name, options)?;
+                writer.write_all(xml.as_bytes())?;
+            }
+            None => writer.raw_copy_file(file)?,
+        }
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+/// Retrieves a compressed and optionally encrypted payload hidden using [`embed_container`] from a document
+///
+/// The payload of the first content file it can be retrieved from is returned.
+pub fn extract_container(
+    document: &[u8],
+    container: Container,
+    key: Option<&str>,
+) -> Result<Vec<u8>, Error> {
+    let mut result = Err(Error::NoStrings);
+    for found in reveal_files(document, container, key)? {
+        result = found.map(|f| f.payload);
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+/// Retrieves every payload hidden using [`embed_container`] from a document, along with the content file it was found in
+/// and the text of the first and last text runs carrying it, which locate it inside of the file
+///
+/// Content files which don't carry a payload that can be retrieved are skipped.
+pub fn locate_container(
+    document: &[u8],
+    container: Container,
+    key: Option<&str>,
+) -> Result<Vec<Found>, Error> {
+    Ok(reveal_files(document, container, key)?
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect())
+}
+
+/// Reveals the payload of each content file of a document which contains zero-width characters
+fn reveal_files(
+    document: &[u8],
+    container: Container,
+    key: Option<&str>,
+) -> Result<Vec<Result<Found, Error>>, Error> {
+    let mut archive = ZipArchive::new(Cursor::new(document))?;
+
+    let mut revealed = Vec::new();
+    for file in container.files(&mut archive)? {
+        let xml = read_file(&mut archive, &file)?;
+        let concealed = container.concealed(&xml);
+        if concealed.is_empty() {
+            continue;
+        }
+
+        let runs: Vec<String> = container
+            .stripped_runs(&xml)
+            .into_iter()
+            .filter(|(_, found)| *found)
+            .map(|(text, _)| text.trim().to_string())
+            .collect();
+        revealed.push(
+            reveal(&concealed, key)
+                .map_err(Error::from)
+                .map(|payload| Found {
+                    file,
+                    first: runs.first().cloned().unwrap_or_default(),
+                    last: runs.last().cloned().unwrap_or_default(),
+                    payload,
+                }),
+        );
+    }
+    Ok(revealed)
+}
+
+/// Finds the XHTML chapters of an EPUB publication in reading order, as listed by the spine of its package document
+fn spine<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Vec<String>, Error> {
+    let container = read_file(archive, "META-INF/container.xml")?;
+    let package = tags(&container, "rootfile")
+        .find_map(|t| attribute(t, "full-path"))
+        .ok_or(ZipError::FileNotFound)?;
+    let base = package.rfind('/').map_or("", |i| &package[..i]);
+    let package = read_file(archive, &resolve("", package))?;
+
+    let items: Vec<(&str, &str)> = tags(&package, "item")
+        .filter(|t| attribute(t, "media-type") == Some("application/xhtml+xml"))
+        .filter_map(|t| Some((attribute(t, "id")?, attribute(t, "href")?)))
+        .collect();
+    Ok(tags(&package, "itemref")
+        .filter_map(|t| attribute(t, "idref"))
+        .filter_map(|id| items.iter().find(|(i, _)| *i == id))
+        .map(|(_, href)| resolve(base, href))
+        .collect())
+}
+
+/// Reads a UTF-8 encoded file from a zip container
+fn read_file<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<String, Error> {
+    let mut content = String::new();
+    archive.by_name(name)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Finds the text runs of an XML document which contain more than whitespace,
+/// only considering text directly inside one of the provided elements
+///
+/// Field results of Office Open XML documents are skipped since they are regenerated when fields are updated.
+fn runs(xml: &str, elements: &[&str]) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut field_result = false;
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while let Some(offset) = xml[i..].find('<') {
+        let start = i + offset;
+        let eligible = !field_result && stack.last().is_some_and(|e| elements.contains(e));
+        if eligible && !xml[text_start..start].trim().is_empty() {
+            runs.push(text_start..start);
+        }
+
+        let rest = &xml[start..];
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map_or(xml.len(), |e| start + e + 3)
+        } else if rest.starts_with("<![CDATA[") {
+            rest.find("]]>").map_or(xml.len(), |e| start + e + 3)
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            rest.find('>').map_or(xml.len(), |e| start + e + 1)
+        } else {
+            let len = tag_len(rest);
+            let tag = &rest[..len];
+            let closing = tag.starts_with("</");
+            let name_start = if closing { 2 } else { 1 };
+            let name = tag[name_start..]
+                .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                .next()
+                .unwrap_or_default();
+
+            if closing {
+                stack.pop();
+            } else if !tag.ends_with("/>") {
+                stack.push(name);
+            }
+            if name == "w:fldChar" {
+                if tag.contains("w:fldCharType=\"separate\"") {
+                    field_result = true;
+                } else if tag.contains("w:fldCharType=\"end\"") {
+                    field_result = false;
+                }
+            }
+            start + len
+        };
+
+        text_start = end;
+        i = end;
+    }
+
+    runs
+}
+
+/// Returns the length of a tag at the start of some markup, ignoring `>` inside of attribute values
+fn tag_len(markup: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in markup.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return i + 1,
+            _ => (),
+        }
+    }
+    markup.len()
+}
+
+/// Iterates over the tags of an XML document with the provided local name
+fn tags<'a>(xml: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    xml.match_indices('<').filter_map(move |(i, _)| {
+        let tag = &xml[i..i + tag_len(&xml[i..])];
+        let tag_name = tag[1..]
+            .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .next()?;
+        (tag_name.rsplit(':').next() == Some(name)).then_some(tag)
+    })
+}
+
+/// Returns the value of an attribute of a tag, without decoding character references
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(i) = rest.find(name) {
+        let preceded = rest[..i].ends_with(char::is_whitespace);
+        rest = &rest[i + name.len()..];
+        let value = match rest.trim_start().strip_prefix('=') {
+            Some(value) if preceded => value.trim_start(),
+            _ => continue,
+        };
+        let quote = value.chars().next().filter(|q| *q == '"' || *q == '\'')?;
+        return value[1..].find(quote).map(|e| &value[1..1 + e]);
+    }
+    None
+}
+
+/// Resolves a relative URL found in a file of a container to the name of the file it points to
+fn resolve(base: &str, href: &str) -> String {
+    let path = href.split('#').next().unwrap_or_default();
+    let mut segments: Vec<&str> = base.split('/').filter(|s| !s.is_empty()).collect();
+    for segment in path.split('/') {
+        match segment {
+            ".." => {
+                segments.pop();
+            }
+            "" | "." => (),
+            _ => segments.push(segment),
+        }
+    }
+
+    // names are stored unescaped in the container
+    let path = segments.join("/");
+    let bytes = path.as_bytes();
+    let mut name = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|h| bytes[i] == b'%' && h.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|h| u8::from_str_radix(core::str::from_utf8(h).ok()?, 16).ok());
+        match escaped {
+            Some(b) => {
+                name.push(b);
+                i += 3;
+            }
+            None => {
+                name.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&name).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{embed_container, extract_container, locate_container, Container};
+    use std::io::{Cursor, Read};
+
+    const SRC: &[u8] = include_bytes!("../../samples/lorem.txt");
+
+    fn files(document: &[u8], container: Container) -> Vec<String> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(document)).unwrap();
+        container.files(&mut archive).unwrap()
+    }
+
+    fn runs(document: &[u8], container: Container) -> Vec<Vec<String>> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(document)).unwrap();
+        let mut runs = Vec::new();
+        for name in container.files(&mut archive).unwrap() {
+            let xml = super::read_file(&mut archive, &name).unwrap();
+            let file_runs = container.text_runs(&xml).into_iter();
+            runs.push(file_runs.map(|r| xml[r].to_string()).collect());
+        }
+        runs
+    }
+
+    fn assert_round_trip(document: &[u8], container: Container) {
+        let embedded = embed_container(document, container, SRC, Some("secret"), None).unwrap();
+        assert_eq!(
+            SRC,
+            extract_container(&embedded, container, Some("secret"))
+                .unwrap()
+                .as_slice()
+        );
+
+        let visible: Vec<Vec<String>> = runs(&embedded, container)
+            .iter()
+            .map(|f| {
+                f.iter()
+                    .map(|r| r.chars().filter(|c| !crate::is_zw(*c)).collect())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(visible, runs(document, container));
+
+        // every other file is copied untouched
+        let content = files(document, container);
+        let mut original = zip::ZipArchive::new(Cursor::new(document)).unwrap();
+        let mut embedded = zip::ZipArchive::new(Cursor::new(&embedded[..])).unwrap();
+        assert_eq!(original.len(), embedded.len());
+        for i in 0..original.len() {
+            let mut original = original.by_index(i).unwrap();
+            let mut embedded = embedded.by_index(i).unwrap();
+            assert_eq!(original.name().unwrap(), embedded.name().unwrap());
+            assert_eq!(original.compression(), embedded.compression());
+
+            let (mut a, mut b) = (Vec::new(), Vec::new());
+            original.read_to_end(&mut a).unwrap();
+            embedded.read_to_end(&mut b).unwrap();
+            if a != b {
+                assert!(content.iter().any(|f| *f == original.name().unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn docx_round_trip() {
+        let document = include_bytes!("../../samples/report.docx");
+        assert_eq!(
+            runs(document, Container::Docx),
+            [[
+                "Quarterly report",
+                "The numbers for this quarter look ",
+                "good",
+                ", but we will need to talk about the marketing budget.",
+                "Confidential &amp; internal"
+            ]]
+        );
+        assert_round_trip(document, Container::Docx);
+    }
+
+    #[test]
+    fn odt_round_trip() {
+        let document = include_bytes!("../../samples/report.odt");
+        assert_eq!(
+            runs(document, Container::Odt),
+            [[
+                "Quarterly report",
+                "The numbers for this quarter look ",
+                "good",
+                ", but we will need to talk about the marketing budget.",
+                "Page ",
+                "Confidential &amp; internal"
+            ]]
+        );
+        assert_round_trip(document, Container::Odt);
+    }
+
+    #[test]
+    fn epub_chapters() {
+        let document = include_bytes!("../../samples/novel.epub");
+        assert_eq!(
+            files(document, Container::Epub),
+            ["OEBPS/Text/chapter 1.xhtml", "OEBPS/Text/chapter 2.xhtml"]
+        );
+        assert_eq!(
+            runs(document, Container::Epub),
+            [
+                vec![
+                    "One",
+                    "Chapter one",
+                    "It was a dark and ",
+                    "stormy",
+                    " night."
+                ],
+                vec!["Two", "Chapter two", "The morning brought calm seas."]
+            ]
+        );
+        assert_round_trip(document, Container::Epub);
+
+        let embedded = embed_container(document, Container::Epub, SRC, None, None).unwrap();
+        let found = locate_container(&embedded, Container::Epub, None).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].file, "OEBPS/Text/chapter 2.xhtml");
+        assert_eq!(found[0].first, "One");
+        assert_eq!(found[0].last, "night.");
+        assert_eq!(found[1].last, "The morning brought calm seas.");
+
+        // runs written using character references are located too
+        assert_eq!(
+            crate::formats::html::strip(" night&zwnj;&#x2060;. &amp;"),
+            (" night. &amp;".to_string(), true)
+        );
+        assert!(found.iter().all(|f| f.payload == SRC));
+        assert!(locate_container(&embedded, Container::Epub, Some("secret"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn container_errors() {
+        assert!(embed_container(b"not a zip", Container::Docx, SRC, None, None).is_err());
+        assert!(matches!(
+            embed_container(
+                include_bytes!("../../samples/report.odt"),
+                Container::Docx,
+                SRC,
+                None,
+                None
+            ),
+            Err(crate::formats::Error::Zip(_))
+        ));
+        assert_eq!(Container::from_extension("odt"), Some(Container::Odt));
+
+        assert_eq!(
+            super::resolve("OEBPS", "../a/./b%20c.xhtml#d"),
+            "a/b c.xhtml"
+        );
+        let tag = "<item id=\"a\" href='b' media-type=\"c\"/>";
+        assert_eq!(super::attribute(tag, "href"), Some("b"));
+        assert_eq!(super::attribute(tag, "type"), None);
+    }
+}
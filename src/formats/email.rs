@@ -0,0 +1,499 @@
+//! RFC 5322 email messages, using text parts only
+//!
+//! Every `text/plain` and `text/html` part of the message carries the whole payload, since mail clients usually only
+//! display one of the alternatives. Modified parts are encoded as UTF-8 using quoted-printable, or base64 if they already
+//! were, so the zero-width characters survive transports which aren't 8-bit clean.
+
+use super::{html, insert, Error};
+use crate::camo::reveal;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::ops::Range;
+
+/// Maximum length of an encoded body line, excluding the line ending
+const LINE_LEN: usize = 76;
+
+/// Hides a concealed payload in every text part of a message
+pub(super) fn embed(message: &str, concealed: &str) -> Result<String, Error> {
+    let newline = if message.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+
+    let mut embedded = String::with_capacity(message.len() + concealed.len() * 4);
+    let mut cursor = 0;
+    for part in text_parts(message) {
+        let existing = match part.html {
+            true => html::extract(&part.text),
+            false => extract_plain(&part.text),
+        };
+        if !existing.is_empty() {
+            return Err(Error::ZeroWidthText);
+        }
+
+        let text = match part.html {
+            true => html::embed(&part.text, concealed, false),
+            false => embed_plain(&part.text, concealed),
+        };
+        let text = match text {
+            Ok(t) => t,
+            Err(Error::NoStrings) => continue,
+            Err(e) => return Err(e),
+        };
+
+        let (headers, body) = split(&message[part.range.clone()]);
+        let headers = set_header(
+            headers,
+            "Content-Type",
+            &with_charset(&part.content_type),
+            newline,
+        );
+        embedded.push_str(&message[cursor..part.range.start]);
+        if part.base64 {
+            embedded.push_str(&headers);
+            embedded.push_str(newline);
+            // text bodies use canonical CRLF line endings before being base64 encoded
+            embedded.push_str(&encode_base64(
+                text.replace('\n', "\r\n").as_bytes(),
+                newline,
+            ));
+            if body.ends_with('\n') {
+                embedded.push_str(newline);
+            }
+        } else {
+            let headers = set_header(
+                &headers,
+                "Content-Transfer-Encoding",
+                "quoted-printable",
+                newline,
+            );
+            embedded.push_str(&headers);
+            embedded.push_str(newline);
+            embedded.push_str(&encode_quoted_printable(&text, newline));
+        }
+        cursor = part.range.end;
+    }
+
+    if cursor == 0 {
+        return Err(Error::NoStrings);
+    }
+    embedded.push_str(&message[cursor..]);
+
+    // messages without a MIME version can't declare a transfer encoding
+    let (headers, _) = split(&embedded);
+    if header(headers, "MIME-Version").is_none() {
+        embedded = set_header(headers, "MIME-Version", "1.0", newline) + &embedded[headers.len()..];
+    }
+    Ok(embedded)
+}
+
+/// Retrieves a payload hidden using [`embed`] from the first text part of a message which carries it
+pub(super) fn extract(message: &str, key: Option<&str>) -> Result<Vec<u8>, Error> {
+    let mut result = Err(Error::NoStrings);
+    for part in text_parts(message) {
+        let concealed = match part.html {
+            true => html::extract(&part.text),
+            false => extract_plain(&part.text),
+        };
+        if concealed.is_empty() {
+            continue;
+        }
+
+        result = reveal(&concealed, key).map_err(Error::from);
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+/// Hides a concealed payload at the end of the lines of a plain text body,
+/// skipping quoted lines and signatures
+fn embed_plain(text: &str, concealed: &str) -> Result<String, Error> {
+    let positions: Vec<usize> = prose_lines(text)
+        .into_iter()
+        .map(|l| l.start + text[l].trim_end().len())
+        .collect();
+    insert(text, &positions, concealed, String::push_str)
+}
+
+/// Collects the zero-width characters of the lines of a plain text body where a payload would be hidden
+fn extract_plain(text: &str) -> String {
+    prose_lines(text)
+        .into_iter()
+        .flat_map(|l| text[l].chars())
+        .filter(|c| crate::is_zw(*c))
+        .collect()
+}
+
+/// Finds the non blank lines of a plain text body which aren't quoted or part of the signature
+fn prose_lines(text: &str) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in text.split('\n') {
+        let range = start..start + line.len();
+        start = range.end + 1;
+
+        let line = line.trim_end_matches('\r');
+        if line == "-- " {
+            break;
+        }
+        if !line.trim().is_empty() && !line.starts_with('>') {
+            lines.push(range);
+        }
+    }
+    lines
+}
+
+/// Text part of a message
+struct TextPart {
+    /// Range of the part, including its headers
+    range: Range<usize>,
+    /// Value of the `Content-Type` header
+    content_type: String,
+    /// Whether the part is `text/html` instead of `text/plain`
+    html: bool,
+    /// Whether the part is base64 encoded
+    base64: bool,
+    /// Decoded text, using `\n` line endings
+    text: String,
+}
+
+/// Finds the text parts of a message which can be decoded, in order
+fn text_parts(message: &str) -> Vec<TextPart> {
+    let mut parts = Vec::new();
+    collect_text_parts(message, 0, &mut parts);
+    parts
+}
+
+/// Finds the text parts of a MIME entity located at an offset in the message
+fn collect_text_parts(entity: &str, offset: usize, parts: &mut Vec<TextPart>) {
+    let (headers, body) = split(entity);
+    let content_type =
+        header(headers, "Content-Type").map_or_else(|| String::from("text/plain"), |(_, v)| v);
+    let mut params = params(&content_type);
+    let mime = params.next().unwrap_or_default().to_ascii_lowercase();
+    let params: Vec<_> = params.collect();
+    let param = |name: &str| {
+        params
+            .iter()
+            .filter_map(|p| p.split_once('='))
+            .find(|(n, _)| n.trim().eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim().trim_matches('"').to_string())
+    };
+
+    if mime.starts_with("multipart/") {
+        if let Some(boundary) = param("boundary") {
+            let body_offset = offset + entity.len() - body.len();
+            for part in multipart(body, &boundary) {
+                collect_text_parts(&body[part.clone()], body_offset + part.start, parts);
+            }
+        }
+        return;
+    }
+
+    let attachment = header(headers, "Content-Disposition")
+        .is_some_and(|(_, v)| v.trim().to_ascii_lowercase().starts_with("attachment"));
+    let utf8 = param("charset")
+        .is_none_or(|c| c.eq_ignore_ascii_case("utf-8") || c.eq_ignore_ascii_case("us-ascii"));
+    if !(mime == "text/plain" || mime == "text/html") || attachment || !utf8 {
+        return;
+    }
+
+    let encoding = header(headers, "Content-Transfer-Encoding")
+        .map(|(_, v)| v.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let decoded = match encoding.as_str() {
+        "quoted-printable" => decode_quoted_printable(body),
+        "base64" => match BASE64.decode(body.split_ascii_whitespace().collect::<String>()) {
+            Ok(d) => d,
+            Err(_) => return,
+        },
+        _ => body.as_bytes().to_vec(),
+    };
+    if let Ok(text) = String::from_utf8(decoded) {
+        parts.push(TextPart {
+            range: offset..offset + entity.len(),
+            content_type,
+            html: mime == "text/html",
+            base64: encoding == "base64",
+            text: text.replace("\r\n", "\n"),
+        });
+    }
+}
+
+/// Splits a MIME entity into its headers, including the line ending of the last one, and its body
+fn split(entity: &str) -> (&str, &str) {
+    let mut start = 0;
+    for line in entity.split_inclusive('\n') {
+        if line.trim_end_matches(['\r', '\n']).is_empty() {
+            return (&entity[..start], &entity[start + line.len()..]);
+        }
+        start += line.len();
+    }
+    (entity, "")
+}
+
+/// Finds a header field, returning its range, including folded lines but excluding the final line ending,
+/// and its unfolded value
+fn header(headers: &str, name: &str) -> Option<(Range<usize>, String)> {
+    let mut start = 0;
+    let mut found: Option<(Range<usize>, String)> = None;
+    for line in headers.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        let range = start..start + content.len();
+        start += line.len();
+
+        match &mut found {
+            Some((r, v)) if content.starts_with([' ', '\t']) => {
+                r.end = range.end;
+                v.push_str(content);
+            }
+            Some(_) => break,
+            None => {
+                if let Some((n, v)) = content.split_once(':') {
+                    if n.trim().eq_ignore_ascii_case(name) {
+                        found = Some((range, v.trim().to_string()));
+                    }
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Replaces the value of a header field, or adds it if it isn't present
+fn set_header(headers: &str, name: &str, value: &str, newline: &str) -> String {
+    match header(headers, name) {
+        Some((range, _)) => format!(
+            "{}{}: {}{}",
+            &headers[..range.start],
+            name,
+            value,
+            &headers[range.end..]
+        ),
+        None => format!("{}{}: {}{}", headers, name, value, newline),
+    }
+}
+
+/// Splits a structured header value on semicolons outside of quoted strings
+fn params(value: &str) -> impl Iterator<Item = &str> {
+    let mut quoted = false;
+    value
+        .split(move |c| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == ';' && !quoted
+        })
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+}
+
+/// Returns a `Content-Type` header value with its charset set to UTF-8
+fn with_charset(content_type: &str) -> String {
+    let mut params: Vec<&str> = params(content_type)
+        .filter(|p| !p.to_ascii_lowercase().starts_with("charset="))
+        .collect();
+    params.push("charset=utf-8");
+    params.join("; ")
+}
+
+/// Finds the parts of a multipart body
+fn multipart(body: &str, boundary: &str) -> Vec<Range<usize>> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut part_start: Option<usize> = None;
+    let mut start = 0;
+    for line in body.split_inclusive('\n') {
+        let content = line.trim_end();
+        if content == delimiter || content == format!("{}--", delimiter) {
+            if let Some(s) = part_start {
+                // the line ending before a delimiter is part of the delimiter
+                let end = body[..start]
+                    .strip_suffix('\n')
+                    .map_or(start, |b| b.strip_suffix('\r').unwrap_or(b).len());
+                parts.push(s..end.max(s));
+            }
+            if content != delimiter {
+                break;
+            }
+            part_start = Some(start + line.len());
+        }
+        start += line.len();
+    }
+    parts
+}
+
+/// Encodes text as quoted-printable, using `\n` as hard line breaks
+fn encode_quoted_printable(text: &str, newline: &str) -> String {
+    let mut encoded = String::with_capacity(text.len() * 3);
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            encoded.push_str(newline);
+        }
+
+        let bytes = line.as_bytes();
+        let mut len = 0;
+        for (j, b) in bytes.iter().enumerate() {
+            let literal = matches!(b, b'!'..=b'<' | b'>'..=b'~')
+                || (matches!(b, b' ' | b'\t') && j + 1 < bytes.len());
+            let token_len = if literal { 1 } else { 3 };
+            if len + token_len > LINE_LEN - 1 {
+                encoded.push('=');
+                encoded.push_str(newline);
+                len = 0;
+            }
+            if literal {
+                encoded.push(*b as char);
+            } else {
+                encoded.push_str(&format!("={:02X}", b));
+            }
+            len += token_len;
+        }
+    }
+    encoded
+}
+
+/// Decodes a quoted-printable body, using `\n` as line endings
+fn decode_quoted_printable(body: &str) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(body.len());
+    let lines: Vec<&str> = body.split('\n').collect();
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.trim_end_matches(['\r', ' ', '\t']);
+        let (line, soft) = match line.strip_suffix('=') {
+            Some(l) => (l, true),
+            None => (line, false),
+        };
+
+        let bytes = line.as_bytes();
+        let mut j = 0;
+        while j < bytes.len() {
+            let byte = match bytes[j] {
+                b'=' => line
+                    .get(j + 1..j + 3)
+                    .and_then(|h| u8::from_str_radix(h, 16).ok()),
+                _ => None,
+            };
+            match byte {
+                Some(b) => {
+                    decoded.push(b);
+                    j += 3;
+                }
+                None => {
+                    decoded.push(bytes[j]);
+                    j += 1;
+                }
+            }
+        }
+        if !soft && i + 1 < lines.len() {
+            decoded.push(b'\n');
+        }
+    }
+    decoded
+}
+
+/// Encodes data as base64, wrapping lines
+fn encode_base64(data: &[u8], newline: &str) -> String {
+    let encoded = BASE64.encode(data);
+    let lines: Vec<&str> = encoded
+        .as_bytes()
+        .chunks(LINE_LEN)
+        // base64 is always ASCII
+        .map(|c| std::str::from_utf8(c).unwrap())
+        .collect();
+    lines.join(newline)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::{embed, extract, Format};
+
+    const SRC: &[u8] = include_bytes!("../../samples/lorem.txt");
+
+    fn assert_transport_safe(message: &str) {
+        assert!(message.is_ascii());
+        assert!(message.lines().all(|l| l.len() <= 998));
+    }
+
+    #[test]
+    fn plain_round_trip() {
+        let message = include_str!("../../samples/plain.eml");
+        let embedded = embed(message, Format::Email, SRC, Some("secret"), None).unwrap();
+        assert_transport_safe(&embedded);
+        assert!(embedded.contains("Content-Type: text/plain; format=flowed; charset=utf-8\n"));
+        assert!(embedded.contains("Content-Transfer-Encoding: quoted-printable\n"));
+        assert!(embedded.contains("\n> Quoted lines are left alone.\n"));
+        assert!(embedded.ends_with("\n--=20\nSomeone\n"));
+
+        assert_eq!(
+            SRC,
+            extract(&embedded, Format::Email, Some("secret"))
+                .unwrap()
+                .as_slice()
+        );
+    }
+
+    #[test]
+    fn multipart_round_trip() {
+        let message = include_str!("../../samples/alternative.eml");
+        let embedded = embed(message, Format::Email, SRC, Some("secret"), None).unwrap();
+        assert_transport_safe(&embedded);
+        assert!(embedded.contains("Content-Transfer-Encoding: base64\r\n"));
+        assert!(embedded.contains("\r\n\r\nVGhpcyBhdHRhY2htZW50IGlzIG5vdCB0b3VjaGVkLg==\r\n"));
+        assert_eq!(
+            SRC,
+            extract(&embedded, Format::Email, Some("secret"))
+                .unwrap()
+                .as_slice()
+        );
+
+        // the payload is still available if one of the alternatives is removed
+        let parts = super::text_parts(&embedded);
+        assert_eq!(parts.len(), 2);
+        let without_plain = [
+            &embedded[..parts[0].range.start],
+            &embedded[parts[0].range.end..],
+        ]
+        .concat();
+        assert_eq!(
+            SRC,
+            extract(&without_plain, Format::Email, Some("secret"))
+                .unwrap()
+                .as_slice()
+        );
+    }
+
+    #[test]
+    fn mime_headers() {
+        let message = "From: someone@example.com\nSubject: Hi\n\nHello there\n";
+        let embedded = embed(message, Format::Email, SRC, None, None).unwrap();
+        assert!(embedded.starts_with("From: someone@example.com\nSubject: Hi\nContent-Type: text/plain; charset=utf-8\nContent-Transfer-Encoding: quoted-printable\nMIME-Version: 1.0\n\n"));
+        assert_eq!(
+            SRC,
+            extract(&embedded, Format::Email, None).unwrap().as_slice()
+        );
+
+        // base64 text is re-encoded with CRLF line endings
+        let message = "MIME-Version: 1.0\nContent-Transfer-Encoding: base64\n\nT25lCnR3bwo=\n";
+        let embedded = embed(message, Format::Email, SRC, None, None).unwrap();
+        let body = super::split(&embedded)
+            .1
+            .split_ascii_whitespace()
+            .collect::<String>();
+        let decoded =
+            String::from_utf8(base64::Engine::decode(&super::BASE64, body).unwrap()).unwrap();
+        assert!(decoded.starts_with("One"));
+        assert_eq!(decoded.matches("\r\n").count(), 2);
+        assert_eq!(decoded.matches('\n').count(), 2);
+    }
+
+    #[test]
+    fn quoted_printable() {
+        let text = "A line with trailing space \nand a long one \u{200C}\u{200D} ".repeat(8) + "=";
+        let encoded = super::encode_quoted_printable(&text, "\r\n");
+        assert!(encoded.lines().all(|l| l.len() <= super::LINE_LEN));
+        assert_eq!(super::decode_quoted_printable(&encoded), text.as_bytes());
+    }
+}
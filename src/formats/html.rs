@@ -0,0 +1,249 @@
+//! HTML documents, using visible text nodes only
+
+use super::{insert, Error};
+use std::ops::Range;
+
+/// Elements whose content is never considered visible text
+const SKIPPED: [&str; 4] = ["pre", "script", "style", "textarea"];
+/// Elements whose content is raw text which can't contain markup
+const RAW: [&str; 3] = ["script", "style", "textarea"];
+
+/// Hides a concealed payload across the visible text nodes of a document
+pub(super) fn embed(document: &str, concealed: &str, entities: bool) -> Result<String, Error> {
+    // insert right after the content, before any trailing whitespace
+    let positions: Vec<usize> = text_nodes(document)
+        .into_iter()
+        .map(|n| n.start + document[n].trim_end().len())
+        .collect();
+    insert(document, &positions, concealed, |embedded, part| {
+        if entities {
+            part.chars().for_each(|c| embedded.push_str(entity(c)));
+        } else {
+            embedded.push_str(part);
+        }
+    })
+}
+
+/// Collects the zero-width characters of the visible text nodes of a document,
+/// including ones written as character references
+pub(super) fn extract(document: &str) -> String {
+    let mut concealed = String::new();
+    for node in text_nodes(document) {
+        let mut text = &document[node];
+        while let Some(c) = text.chars().next() {
+            if c == '&' {
+                if let Some((c, len)) = parse_entity(text) {
+                    if crate::is_zw(c) {
+                        concealed.push(c);
+                    }
+                    text = &text[len..];
+                    continue;
+                }
+            } else if crate::is_zw(c) {
+                concealed.push(c);
+            }
+            text = &text[c.len_utf8()..];
+        }
+    }
+    concealed
+}
+
+/// Removes the zero-width characters of a text node, including ones written as character references,
+/// returning the remaining text and whether there were any
+pub(super) fn strip(text: &str) -> (String, bool) {
+    let mut stripped = String::with_capacity(text.len());
+    let mut found = false;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let (c, len) = match c {
+            '&' => parse_entity(rest).unwrap_or((c, 1)),
+            _ => (c, c.len_utf8()),
+        };
+        if crate::is_zw(c) {
+            found = true;
+        } else {
+            stripped.push_str(&rest[..len]);
+        }
+        rest = &rest[len..];
+    }
+    (stripped, found)
+}
+
+/// Returns the character reference for a zero-width character
+fn entity(c: char) -> &'static str {
+    match c {
+        '\u{200C}' => "&zwnj;",
+        '\u{200D}' => "&zwj;",
+        '\u{2060}' => "&#x2060;",
+        '\u{2062}' => "&#x2062;",
+        '\u{2063}' => "&#x2063;",
+        '\u{2064}' => "&#x2064;",
+        _ => unreachable!(),
+    }
+}
+
+/// Parses a character reference at the start of some text,
+/// returning the referenced character and the length of the reference
+///
+/// Named references other than the zero-width ones are not recognised.
+fn parse_entity(text: &str) -> Option<(char, usize)> {
+    let end = text.find(';').filter(|e| *e <= 10)?;
+    let name = &text[1..end];
+    let c = match name {
+        "zwnj" => '\u{200C}',
+        "zwj" => '\u{200D}',
+        _ if name.starts_with("#x") || name.starts_with("#X") => {
+            core::char::from_u32(u32::from_str_radix(&name[2..], 16).ok()?)?
+        }
+        _ if name.starts_with('#') => core::char::from_u32(name[1..].parse().ok()?)?,
+        _ => return None,
+    };
+    Some((c, end + 1))
+}
+
+/// Finds the visible text nodes of a document which contain more than whitespace
+pub(super) fn text_nodes(document: &str) -> Vec<Range<usize>> {
+    // lowercasing ASCII keeps byte offsets intact
+    let lower = document.to_ascii_lowercase();
+    let mut nodes = Vec::new();
+    let mut skipped = 0usize;
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while let Some(offset) = lower[i..].find('<') {
+        let start = i + offset;
+        let rest = &lower[start..];
+        // the text before a tag is skipped depending on the elements it is inside of
+        let outer = skipped;
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map_or(lower.len(), |e| start + e + 3)
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest.find('>').map_or(lower.len(), |e| start + e + 1)
+        } else if let Some(tag) = parse_tag(rest) {
+            let end = start + tag.len;
+            if tag.closing {
+                if SKIPPED.contains(&tag.name) {
+                    skipped = skipped.saturating_sub(1);
+                }
+                end
+            } else if tag.self_closing || !SKIPPED.contains(&tag.name) {
+                end
+            } else if RAW.contains(&tag.name) {
+                // the raw content is skipped along with the opening tag
+                let close = format!("</{}", tag.name);
+                lower[end..].find(&close).map_or(lower.len(), |e| end + e)
+            } else {
+                skipped += 1;
+                end
+            }
+        } else {
+            // a lone `<` is part of the text
+            i = start + 1;
+            continue;
+        };
+
+        push_node(document, text_start..start, outer, &mut nodes);
+        text_start = end;
+        i = end;
+    }
+    push_node(document, text_start..document.len(), skipped, &mut nodes);
+
+    nodes
+}
+
+/// Adds a text node if it is visible and contains more than whitespace
+fn push_node(document: &str, node: Range<usize>, skipped: usize, nodes: &mut Vec<Range<usize>>) {
+    if skipped == 0 && !document[node.clone()].trim().is_empty() {
+        nodes.push(node);
+    }
+}
+
+/// Start or end tag
+struct Tag<'a> {
+    name: &'a str,
+    closing: bool,
+    self_closing: bool,
+    len: usize,
+}
+
+/// Parses a tag at the start of some lowercased markup
+fn parse_tag(markup: &str) -> Option<Tag<'_>> {
+    let bytes = markup.as_bytes();
+    let closing = bytes.get(1) == Some(&b'/');
+    let name_start = if closing { 2 } else { 1 };
+    if !bytes.get(name_start)?.is_ascii_alphabetic() {
+        return None;
+    }
+    let name_end = bytes[name_start..]
+        .iter()
+        .position(|b| !(b.is_ascii_alphanumeric() || *b == b'-' || *b == b':'))
+        .map_or(bytes.len(), |p| name_start + p);
+
+    let mut quote = None;
+    for (i, b) in bytes.iter().enumerate().skip(name_end) {
+        match (quote, *b) {
+            (None, b'"') | (None, b'\'') => quote = Some(*b),
+            (Some(q), b) if q == b => quote = None,
+            (None, b'>') => {
+                return Some(Tag {
+                    name: &markup[name_start..name_end],
+                    closing,
+                    self_closing: bytes[i - 1] == b'/',
+                    len: i + 1,
+                })
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::{embed, extract, Format};
+
+    const SRC: &[u8] = include_bytes!("../../samples/lorem.txt");
+    const DOCUMENT: &str = "<!DOCTYPE html>\n<p class=\"a b\">Some <b>text</b> <!-- a comment --></p>\n<script>let a = '<p> b';</script>\n<pre>keep <i>this</i></pre>\n<textarea>and this</textarea>\n<p>End & more</p>\n";
+
+    #[test]
+    fn text_nodes() {
+        let nodes: Vec<_> = super::text_nodes(DOCUMENT)
+            .into_iter()
+            .map(|n| &DOCUMENT[n])
+            .collect();
+        assert_eq!(nodes, ["Some ", "text", "End & more"]);
+        assert_eq!(super::text_nodes("<pre>a</pre>b").len(), 1);
+    }
+
+    #[test]
+    fn html_round_trip() {
+        // the sample contains a zero-width character inside an attribute which must be ignored
+        let document = include_str!("../../samples/steganography.html");
+        let visible =
+            crate::formats::tests::assert_round_trip(document, Format::Html { entities: false });
+        let original: String = document.chars().filter(|c| !crate::is_zw(*c)).collect();
+        assert_eq!(visible, original);
+
+        let embedded = embed(DOCUMENT, Format::Html { entities: false }, SRC, None, None).unwrap();
+        assert!(embedded.starts_with("<!DOCTYPE html>\n<p class=\"a b\">Some"));
+        assert!(embedded.contains(
+            "<script>let a = '<p> b';</script>\n<pre>keep <i>this</i></pre>\n<textarea>and this</textarea>"
+        ));
+    }
+
+    #[test]
+    fn html_entities() {
+        let format = Format::Html { entities: true };
+        let embedded = embed(DOCUMENT, format, SRC, Some("secret"), None).unwrap();
+        assert!(embedded.is_ascii());
+        assert!(embedded.contains("&zwnj;") || embedded.contains("&zwj;"));
+        assert_eq!(
+            SRC,
+            extract(&embedded, format, Some("secret"))
+                .unwrap()
+                .as_slice()
+        );
+
+        assert!(embed("<p> </p>", format, SRC, None, None).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! Markdown documents, using prose text only
+
+use super::{insert, Error};
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+/// Hides a concealed payload across the prose text of a document
+pub(super) fn embed(document: &str, concealed: &str) -> Result<String, Error> {
+    let positions: Vec<usize> = text_nodes(document)
+        .into_iter()
+        .map(|n| n.start + document[n].trim_end().len())
+        .collect();
+    insert(document, &positions, concealed, String::push_str)
+}
+
+/// Collects the zero-width characters of the prose text of a document
+pub(super) fn extract(document: &str) -> String {
+    text_nodes(document)
+        .into_iter()
+        .flat_map(|n| document[n].chars())
+        .filter(|c| crate::is_zw(*c))
+        .collect()
+}
+
+/// Finds the prose text nodes of a document which contain more than whitespace
+///
+/// Text inside code blocks and images is skipped, as well as the text of links which is used to resolve them,
+/// since modifying it would change the rendered document.
+fn text_nodes(document: &str) -> Vec<Range<usize>> {
+    let mut nodes = Vec::new();
+    let mut skipped = 0usize;
+    let mut link_skipped = false;
+
+    let parser = Parser::new_ext(
+        document,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH,
+    );
+    for (event, range) in parser.into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Start(Tag::Image { .. }) => skipped += 1,
+            Event::End(TagEnd::CodeBlock) | Event::End(TagEnd::Image) => skipped -= 1,
+            Event::Start(Tag::Link { link_type, .. }) => {
+                link_skipped = !matches!(link_type, LinkType::Inline | LinkType::Reference)
+            }
+            Event::End(TagEnd::Link) => link_skipped = false,
+            Event::Text(_)
+                if skipped == 0 && !link_skipped && !document[range.clone()].trim().is_empty() =>
+            {
+                nodes.push(range)
+            }
+            _ => (),
+        }
+    }
+
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::Format;
+
+    #[cfg(feature = "html")]
+    const SRC: &[u8] = include_bytes!("../../samples/lorem.txt");
+    const DOCUMENT: &str =
+        "# Title\n\nSome *prose* with `code`, a [link](https://example.com \"title\"), \
+        a [reference], <https://example.com> and ![an image](image.png).\n\n\
+        ```rust\nlet a = 1;\n```\n\n    indented code\n\n\
+        | a | b |\n|---|---|\n| c | d |\n\n\
+        [reference]: https://example.com\n";
+
+    #[cfg(feature = "html")]
+    fn render(markdown: &str) -> String {
+        use pulldown_cmark::{html, Options, Parser};
+
+        let mut rendered = String::new();
+        let parser = Parser::new_ext(
+            markdown,
+            Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH,
+        );
+        html::push_html(&mut rendered, parser);
+        rendered
+    }
+
+    #[test]
+    fn text_nodes() {
+        let nodes: Vec<_> = super::text_nodes(DOCUMENT)
+            .into_iter()
+            .map(|n| &DOCUMENT[n])
+            .collect();
+        assert_eq!(
+            nodes,
+            [
+                "Title", "Some ", "prose", " with ", ", a ", "link", ", a ", ", ", " and ", ".",
+                "a", "b", "c", "d"
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_round_trip() {
+        let visible = crate::formats::tests::assert_round_trip(
+            include_str!("../../README.md"),
+            Format::Markdown,
+        );
+        assert_eq!(visible, include_str!("../../README.md"));
+    }
+
+    #[cfg(feature = "html")]
+    #[test]
+    fn markdown_render() {
+        use crate::formats::{embed, extract};
+
+        let embedded = embed(DOCUMENT, Format::Markdown, SRC, Some("secret"), None).unwrap();
+        let rendered = render(&embedded);
+
+        let stripped: String = rendered.chars().filter(|c| !crate::is_zw(*c)).collect();
+        assert_eq!(stripped, render(DOCUMENT));
+        assert_eq!(
+            SRC,
+            extract(&rendered, Format::Html { entities: false }, Some("secret"))
+                .unwrap()
+                .as_slice()
+        );
+    }
+}
@@ -0,0 +1,506 @@
+//! Source files, using comments only
+
+use super::{insert, Error};
+use pulldown_cmark::{Event, Parser, Tag};
+use std::ops::Range;
+
+/// JavaScript keywords which can be followed by an expression, and so by a regular expression literal
+const EXPRESSION_KEYWORDS: [&str; 15] = [
+    "await",
+    "case",
+    "delete",
+    "do",
+    "else",
+    "in",
+    "instanceof",
+    "new",
+    "of",
+    "return",
+    "throw",
+    "typeof",
+    "void",
+    "yield",
+    "extends",
+];
+
+/// Prefixes of Python comments read by tools, such as type checkers, linters and formatters
+const PYTHON_DIRECTIVES: [&str; 14] = [
+    "type:", "noqa", "pylint:", "mypy:", "pyright:", "pyre-", "fmt:", "isort:", "pragma:", "nosec",
+    "ruff:", "flake8", "-*-", "vim:",
+];
+
+/// Prefixes of JavaScript comments read by tools, such as linters, type checkers and bundlers
+///
+/// `#` and `@` cover source map URLs and annotations like `@ts-ignore`, `@flow` or `#__PURE__`.
+const JAVASCRIPT_DIRECTIVES: [&str; 14] = [
+    "#",
+    "@",
+    "/ <reference",
+    "eslint",
+    "prettier-",
+    "istanbul ",
+    "c8 ",
+    "jshint",
+    "jslint",
+    "global ",
+    "globals ",
+    "webpack",
+    "tslint:",
+    "biome-ignore",
+];
+
+/// Prefixes of Rust comments read by tools, such as rustfmt and the compiler test suite
+const RUST_DIRECTIVES: [&str; 9] = [
+    "@",
+    "~",
+    "rustfmt",
+    "clippy",
+    "ignore-tidy",
+    "compile-flags:",
+    "edition:",
+    "revisions:",
+    "error-pattern:",
+];
+
+/// Programming language of a source file
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Language {
+    JavaScript,
+    Python,
+    Rust,
+}
+impl Language {
+    /// Guesses the language of a source file from its extension
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" => Some(Self::JavaScript),
+            "py" | "pyw" | "pyi" => Some(Self::Python),
+            "rs" => Some(Self::Rust),
+            _ => None,
+        }
+    }
+}
+
+/// Hides a concealed payload across the comments of a source file
+pub(super) fn embed(source: &str, concealed: &str, language: Language) -> Result<String, Error> {
+    // insert at the end of the comment content, before any trailing whitespace or closing delimiter
+    let positions: Vec<usize> = comments(source, language)
+        .into_iter()
+        .map(|c| c.start + source[c].trim_end().len())
+        .collect();
+    insert(source, &positions, concealed, String::push_str)
+}
+
+/// Collects the zero-width characters of the comments of a source file
+pub(super) fn extract(source: &str, language: Language) -> String {
+    comments(source, language)
+        .into_iter()
+        .flat_map(|c| source[c].chars())
+        .filter(|c| crate::is_zw(*c))
+        .collect()
+}
+
+/// Finds the content of the comments of a source file, excluding their delimiters
+///
+/// The lexer only knows about comments, string literals and JavaScript regular expression literals,
+/// which is enough to never mistake one for the other. A slash starts a regular expression wherever an expression
+/// can start, which is decided from the previous significant token.
+/// Comments read by tools are skipped since modifying them would change how the file is checked or run:
+/// Python shebang and encoding lines, comments starting with a known directive, and Rust doc comments containing
+/// code blocks, which are compiled as doctests.
+fn comments(source: &str, language: Language) -> Vec<Range<usize>> {
+    let bytes = source.as_bytes();
+    let mut comments = Vec::new();
+    let mut i = 0;
+    // whether the previous significant JavaScript token allows an expression to start after it
+    let mut expression = true;
+
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        i = match (language, bytes[i]) {
+            (Language::Python, b'#') => {
+                let end = line_end(bytes, i);
+                let content = &source[i + 1..end];
+                let header = bytes[..i].iter().filter(|b| **b == b'\n').count() < 2;
+                let encoding =
+                    header && (content.contains("coding:") || content.contains("coding="));
+                let shebang = i == 0 && rest.starts_with(b"#!");
+                if !(shebang || encoding || is_directive(content, language)) {
+                    comments.push(i + 1..end);
+                }
+                end
+            }
+            (Language::Rust, b'/') if is_doc_line(rest) => {
+                let (lines, end) = doc_lines(bytes, i);
+                if !has_code_block(lines.iter().map(|l| &source[l.clone()])) {
+                    comments.extend(lines);
+                }
+                end
+            }
+            (Language::JavaScript, b'/') | (Language::Rust, b'/') if rest.starts_with(b"//") => {
+                let end = line_end(bytes, i);
+                if !is_directive(&source[i + 2..end], language) {
+                    comments.push(i + 2..end);
+                }
+                end
+            }
+            (Language::JavaScript, b'/') | (Language::Rust, b'/') if rest.starts_with(b"/*") => {
+                let end = block_comment_end(bytes, i + 2, language == Language::Rust);
+                let closed = end >= i + 4 && bytes[..end].ends_with(b"*/");
+                let content = i + 2..if closed { end - 2 } else { end };
+                let doc = language == Language::Rust
+                    && (rest.starts_with(b"/*!")
+                        || (rest.starts_with(b"/**") && !rest.starts_with(b"/***")));
+                let code =
+                    doc && has_code_block(block_doc_lines(&source[content.start + 1..content.end]));
+                if !(code || is_directive(&source[content.clone()], language)) {
+                    comments.push(content);
+                }
+                end
+            }
+            (Language::JavaScript, b'/') if expression => {
+                expression = false;
+                skip_regex(bytes, i + 1)
+            }
+            (Language::Python, b'"') | (Language::Python, b'\'') => {
+                let triple = rest.len() >= 3 && rest[1] == rest[0] && rest[2] == rest[0];
+                let quote = &rest[..if triple { 3 } else { 1 }];
+                skip_quoted(bytes, i + quote.len(), quote, triple)
+            }
+            (Language::JavaScript, b'"') | (Language::JavaScript, b'\'') => {
+                expression = false;
+                skip_quoted(bytes, i + 1, &rest[..1], false)
+            }
+            (Language::JavaScript, b'`') | (Language::Rust, b'"') => {
+                expression = false;
+                skip_quoted(bytes, i + 1, &rest[..1], true)
+            }
+            (Language::Rust, b'\'') => skip_char(source, i),
+            (Language::Rust, b'r') => skip_raw_string(bytes, i),
+            (Language::JavaScript, b) if is_js_ident(b) => {
+                let end = i + rest.iter().take_while(|b| is_js_ident(**b)).count();
+                expression = EXPRESSION_KEYWORDS.contains(&&source[i..end]);
+                end
+            }
+            (Language::JavaScript, b) if !b.is_ascii_whitespace() => {
+                expression = !matches!(b, b')' | b']' | b'}');
+                i + 1
+            }
+            _ => i + 1,
+        };
+    }
+
+    comments
+}
+
+/// Returns whether a byte can be part of a JavaScript identifier, keyword or number
+///
+/// Non-ASCII bytes are assumed to be part of identifiers.
+fn is_js_ident(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || !b.is_ascii()
+}
+
+/// Returns whether the content of a comment starts with a directive read by a tool
+fn is_directive(content: &str, language: Language) -> bool {
+    let directives: &[&str] = match language {
+        Language::JavaScript => &JAVASCRIPT_DIRECTIVES,
+        Language::Python => &PYTHON_DIRECTIVES,
+        Language::Rust => &RUST_DIRECTIVES,
+    };
+    let content = content.trim_start();
+    directives.iter().any(|d| {
+        content
+            .get(..d.len())
+            .is_some_and(|p| p.eq_ignore_ascii_case(d))
+    })
+}
+
+/// Returns whether the lines of a Rust doc comment, excluding their delimiters, contain a code block
+///
+/// Lines are unindented the way rustdoc does before being parsed as Markdown,
+/// so that indented code blocks are only found if they are indented relative to the rest of the comment.
+fn has_code_block<'a, I: Iterator<Item = &'a str> + Clone>(lines: I) -> bool {
+    let indent = lines
+        .clone()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let text: String = lines
+        .map(|l| l.get(indent..).unwrap_or("").to_string() + "\n")
+        .collect();
+    Parser::new(&text).any(|e| matches!(e, Event::Start(Tag::CodeBlock(_))))
+}
+
+/// Splits the content of a Rust block doc comment, excluding its delimiters, into lines,
+/// removing the leading asterisks decorating them if every line has one
+fn block_doc_lines(content: &str) -> impl Iterator<Item = &str> + Clone {
+    let decorated = content
+        .lines()
+        .skip(1)
+        .filter(|l| !l.trim().is_empty())
+        .all(|l| l.trim_start().starts_with('*'));
+    content
+        .lines()
+        .enumerate()
+        .map(move |(i, l)| match l.trim_start().strip_prefix('*') {
+            Some(l) if decorated && i > 0 => l,
+            _ => l,
+        })
+}
+
+/// Returns whether a Rust line comment is an outer or inner doc comment
+fn is_doc_line(rest: &[u8]) -> bool {
+    (rest.starts_with(b"///") && !rest.starts_with(b"////")) || rest.starts_with(b"//!")
+}
+
+/// Finds the content of consecutive Rust doc comment lines of the same kind starting at an index,
+/// returning them along with the index of the end of the last one
+fn doc_lines(bytes: &[u8], mut i: usize) -> (Vec<Range<usize>>, usize) {
+    let prefix = &bytes[i..i + 3];
+    let mut lines = Vec::new();
+    loop {
+        let end = line_end(bytes, i);
+        lines.push(i + 3..end);
+
+        let next = end + 1;
+        let indent = bytes.get(next..).map_or(0, |b| {
+            b.iter().take_while(|b| **b == b' ' || **b == b'\t').count()
+        });
+        match bytes.get(next + indent..) {
+            Some(rest) if rest.starts_with(prefix) && is_doc_line(rest) => i = next + indent,
+            _ => return (lines, end),
+        }
+    }
+}
+
+/// Returns the index right after a JavaScript regular expression literal and its flags,
+/// starting right after its opening slash
+///
+/// Literals end at the end of the line if they are not closed.
+fn skip_regex(bytes: &[u8], mut i: usize) -> usize {
+    let mut class = false;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\n' => return i,
+            b'[' => {
+                class = true;
+                i += 1;
+            }
+            b']' => {
+                class = false;
+                i += 1;
+            }
+            b'/' if !class => {
+                i += 1;
+                return i + bytes[i..].iter().take_while(|b| is_js_ident(**b)).count();
+            }
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Returns the index of the end of the line containing an index
+fn line_end(bytes: &[u8], i: usize) -> usize {
+    bytes[i..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map_or(bytes.len(), |p| i + p)
+}
+
+/// Returns the index right after the end of a block comment, starting right after its opening delimiter
+fn block_comment_end(bytes: &[u8], mut i: usize, nested: bool) -> usize {
+    let mut depth = 1;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else if nested && bytes[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// Returns the index right after the closing quote of a string literal, starting right after its opening quote
+///
+/// Single line literals end at the end of the line if they are not closed.
+fn skip_quoted(bytes: &[u8], mut i: usize, quote: &[u8], multiline: bool) -> usize {
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            i += 2;
+        } else if bytes[i..].starts_with(quote) {
+            return i + quote.len();
+        } else if !multiline && bytes[i] == b'\n' {
+            return i;
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// Returns the index right after a Rust character literal, or right after the quote of a lifetime
+fn skip_char(source: &str, i: usize) -> usize {
+    let bytes = source.as_bytes();
+    match source[i + 1..].chars().next() {
+        Some('\\') => bytes
+            .get(i + 3..)
+            .and_then(|b| b.iter().position(|b| *b == b'\''))
+            .map_or(bytes.len(), |p| i + 3 + p + 1),
+        Some(c) if bytes.get(i + 1 + c.len_utf8()) == Some(&b'\'') => i + 1 + c.len_utf8() + 1,
+        _ => i + 1,
+    }
+}
+
+/// Returns the index right after a Rust raw string literal, or right after the `r` if there isn't one
+fn skip_raw_string(bytes: &[u8], i: usize) -> usize {
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let prefixed = match i {
+        0 => true,
+        1 => !is_ident(bytes[0]) || bytes[0] == b'b',
+        _ => !is_ident(bytes[i - 1]) || (bytes[i - 1] == b'b' && !is_ident(bytes[i - 2])),
+    };
+    let hashes = bytes[i + 1..].iter().take_while(|b| **b == b'#').count();
+    if !prefixed || bytes.get(i + 1 + hashes) != Some(&b'"') {
+        return i + 1;
+    }
+
+    let mut closing = vec![b'"'];
+    closing.resize(hashes + 1, b'#');
+    let start = i + 1 + hashes + 1;
+    bytes[start..]
+        .windows(closing.len())
+        .position(|w| w == &closing[..])
+        .map_or(bytes.len(), |p| start + p + closing.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Language;
+    use crate::formats::Format;
+
+    fn assert_code_unchanged(source: &str, language: Language) {
+        let embedded = crate::formats::tests::assert_round_trip(source, Format::Source(language));
+        assert_eq!(embedded, source);
+
+        let code = |source: &str| {
+            let mut code = String::new();
+            let mut cursor = 0;
+            for c in super::comments(source, language) {
+                code.push_str(&source[cursor..c.start]);
+                cursor = c.end;
+            }
+            code + &source[cursor..]
+        };
+        let embedded =
+            crate::formats::embed(source, Format::Source(language), b"payload", None, None)
+                .unwrap();
+        assert_eq!(code(source), code(&embedded));
+    }
+
+    #[test]
+    fn rust_comments() {
+        let source = "let a = \"// not a comment\"; // comment\nlet b = '\"'; let c: &'static str = r#\"/* \" not */\"#;\n/* outer /* inner */ still outer */ fn f<'a>() {}\n";
+        let comments: Vec<_> = super::comments(source, Language::Rust)
+            .into_iter()
+            .map(|c| &source[c])
+            .collect();
+        assert_eq!(comments, [" comment", " outer /* inner */ still outer "]);
+
+        assert_code_unchanged(include_str!("../lib.rs"), Language::Rust);
+    }
+
+    #[test]
+    fn javascript_comments() {
+        let source = "const a = '/* no'; // yes\nconst b = `multi\n// line`; /* block\ncomment */\nconst c = \"\\\"//\";\n";
+        let comments: Vec<_> = super::comments(source, Language::JavaScript)
+            .into_iter()
+            .map(|c| &source[c])
+            .collect();
+        assert_eq!(comments, [" yes", " block\ncomment "]);
+
+        assert_code_unchanged(source, Language::JavaScript);
+    }
+
+    #[test]
+    fn javascript_regex_literals() {
+        let source = "s = s.replace(/\\/*/g, \"\"); // one\nif (/'/.test(s)) x = a / b / c; /* two */\nreturn /[/]\"/i; // three\n";
+        let comments: Vec<_> = super::comments(source, Language::JavaScript)
+            .into_iter()
+            .map(|c| &source[c])
+            .collect();
+        assert_eq!(comments, [" one", " two ", " three"]);
+
+        assert_code_unchanged(source, Language::JavaScript);
+    }
+
+    #[test]
+    fn rust_doc_comments() {
+        let source = "/// Adds one\n///\n/// ```\n/// assert_eq!(f(1), 2);\n/// ```\nfn f(a: u8) -> u8 { a + 1 } // plain\n/// Just prose\nfn g() {}\n/** ```\nlet a = 1;\n``` */\nfn h() {}\n";
+        let comments: Vec<_> = super::comments(source, Language::Rust)
+            .into_iter()
+            .map(|c| &source[c])
+            .collect();
+        assert_eq!(comments, [" plain", " Just prose"]);
+        assert_code_unchanged(source, Language::Rust);
+
+        // indented and tilde fenced code blocks are doctests too, while indenting every line isn't a code block
+        let source = "/// Adds two\n///\n///     assert_eq!(f(1), 3);\nfn f(a: u8) -> u8 { a + 2 }\n//! ~~~\n//! let a = 1;\n//! ~~~\n/**\n * Example:\n *\n *     let b = 2;\n */\nfn g() {}\n///     Indented prose\nfn h() {}\n";
+        let comments: Vec<_> = super::comments(source, Language::Rust)
+            .into_iter()
+            .map(|c| &source[c])
+            .collect();
+        assert_eq!(comments, ["     Indented prose"]);
+        assert_code_unchanged(source, Language::Rust);
+    }
+
+    #[test]
+    fn directives() {
+        let source = "# -*- coding: utf-8 -*-\nimport a  # type: ignore\nb = 1  # noqa: E501\n# pylint: disable=x\n# comment\n";
+        let comments: Vec<_> = super::comments(source, Language::Python)
+            .into_iter()
+            .map(|c| &source[c])
+            .collect();
+        assert_eq!(comments, [" comment"]);
+        assert_code_unchanged(source, Language::Python);
+
+        let source = "// @ts-ignore\nf(); // eslint-disable-line no-undef\n/* istanbul ignore next */ g();\nh(/* webpackChunkName: \"h\" */ 'h');\n// comment\n//# sourceMappingURL=a.js.map\n";
+        let comments: Vec<_> = super::comments(source, Language::JavaScript)
+            .into_iter()
+            .map(|c| &source[c])
+            .collect();
+        assert_eq!(comments, [" comment"]);
+        assert_code_unchanged(source, Language::JavaScript);
+
+        let source =
+            "//@ compile-flags: -O\n// rustfmt-max_width: 80\nfn f() {} //~ ERROR\n// comment\n";
+        let comments: Vec<_> = super::comments(source, Language::Rust)
+            .into_iter()
+            .map(|c| &source[c])
+            .collect();
+        assert_eq!(comments, [" comment"]);
+        assert_code_unchanged(source, Language::Rust);
+    }
+
+    #[test]
+    fn python_comments() {
+        let source = "#!/usr/bin/env python\n# comment\na = '#' + \"'\"  # another\nb = \"\"\"\n# docstring\n\"\"\"\n";
+        let comments: Vec<_> = super::comments(source, Language::Python)
+            .into_iter()
+            .map(|c| &source[c])
+            .collect();
+        assert_eq!(comments, [" comment", " another"]);
+
+        assert_code_unchanged(source, Language::Python);
+        assert_eq!(Language::from_extension("py"), Some(Language::Python));
+    }
+}
@@ -0,0 +1,121 @@
+//! Brotli compression and decompression using the global allocator, without requiring `std`
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use brotli::enc::{BrotliAlloc, BrotliEncoderParams};
+use brotli::{Allocator, CustomRead, CustomWrite, SliceWrapper, SliceWrapperMut};
+
+/// Error returned by Brotli, which is an I/O error when `std` is available
+#[cfg(feature = "std")]
+pub type BrotliError = std::io::Error;
+
+/// Error returned by Brotli when the compressed data is invalid or truncated
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct BrotliError;
+#[cfg(not(feature = "std"))]
+impl core::fmt::Display for BrotliError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid brotli stream")
+    }
+}
+
+/// Returns the error Brotli reports for invalid or truncated compressed data
+fn invalid_stream() -> BrotliError {
+    #[cfg(feature = "std")]
+    return std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid brotli stream");
+    #[cfg(not(feature = "std"))]
+    return BrotliError;
+}
+
+/// Size of the buffers used to feed Brotli
+const BUFFER_LEN: usize = 4096;
+
+/// Heap allocated memory handed out to Brotli
+#[derive(Default)]
+pub(crate) struct HeapMemory<T>(Box<[T]>);
+impl<T> SliceWrapper<T> for HeapMemory<T> {
+    fn slice(&self) -> &[T] {
+        &self.0
+    }
+}
+impl<T> SliceWrapperMut<T> for HeapMemory<T> {
+    fn slice_mut(&mut self) -> &mut [T] {
+        &mut self.0
+    }
+}
+
+/// Brotli allocator using the global allocator
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct HeapAlloc;
+impl<T: Clone + Default> Allocator<T> for HeapAlloc {
+    type AllocatedMemory = HeapMemory<T>;
+
+    fn alloc_cell(&mut self, len: usize) -> Self::AllocatedMemory {
+        HeapMemory(vec![T::default(); len].into_boxed_slice())
+    }
+
+    fn free_cell(&mut self, _data: Self::AllocatedMemory) {}
+}
+impl BrotliAlloc for HeapAlloc {}
+
+/// Reader over a byte slice
+struct SliceReader<'a>(&'a [u8]);
+impl<'a> CustomRead<BrotliError> for SliceReader<'a> {
+    fn read(&mut self, data: &mut [u8]) -> Result<usize, BrotliError> {
+        let len = self.0.len().min(data.len());
+        data[..len].copy_from_slice(&self.0[..len]);
+        self.0 = &self.0[len..];
+        Ok(len)
+    }
+}
+
+/// Writer appending to a vector
+struct VecWriter(Vec<u8>);
+impl CustomWrite<BrotliError> for VecWriter {
+    fn write(&mut self, data: &[u8]) -> Result<usize, BrotliError> {
+        self.0.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> Result<(), BrotliError> {
+        Ok(())
+    }
+}
+
+/// Compresses data using Brotli with the provided quality
+pub(crate) fn compress(data: &[u8], quality: i32) -> Result<Vec<u8>, BrotliError> {
+    let mut writer = VecWriter(Vec::with_capacity(data.len()));
+    brotli::BrotliCompressCustomIo(
+        &mut SliceReader(data),
+        &mut writer,
+        &mut [0; BUFFER_LEN],
+        &mut [0; BUFFER_LEN],
+        &BrotliEncoderParams {
+            quality,
+            size_hint: data.len(),
+            ..Default::default()
+        },
+        HeapAlloc,
+        &mut |_, _, _, _| (),
+        invalid_stream(),
+    )?;
+    Ok(writer.0)
+}
+
+/// Decompresses Brotli compressed data
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>, BrotliError> {
+    let mut writer = VecWriter(Vec::with_capacity(data.len() * 4));
+    brotli::BrotliDecompressCustomIo(
+        &mut SliceReader(data),
+        &mut writer,
+        &mut [0; BUFFER_LEN],
+        &mut [0; BUFFER_LEN],
+        HeapAlloc,
+        HeapAlloc,
+        HeapAlloc,
+        invalid_stream(),
+    )?;
+    Ok(writer.0)
+}
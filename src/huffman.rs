@@ -0,0 +1,394 @@
+use crate::{Error, CHARS};
+use core::iter::FusedIterator;
+
+/// Number of 4 bits patterns
+const NIBBLES: usize = 16;
+/// Longest codeword of a nibble code
+const MAX_NIBBLE_LEN: usize = 3;
+/// Longest codeword of any code
+pub(crate) const MAX_LEN: usize = 5;
+/// Maximum number of nodes in a Huffman tree over bytes
+const MAX_NODES: usize = 2 * 256;
+
+/// Returns the digit represented by a zero-width character
+#[inline]
+pub(crate) fn digit(c: char) -> Result<usize, Error> {
+    CHARS
+        .iter()
+        .position(|zw| *zw == c)
+        .ok_or(Error::InvalidCharacter(c))
+}
+
+/// Computes the lengths of an optimal 6-ary Huffman code for the provided weights
+pub(crate) fn huffman_lengths(weights: &[usize], lengths: &mut [u8]) {
+    let arity = CHARS.len();
+    let n = weights.len();
+    if n <= 1 {
+        lengths.iter_mut().for_each(|l| *l = 1);
+        return;
+    }
+
+    // Every merge of 6 nodes removes 5 of them, so dummy leaves are added until
+    // the merges end up exactly at the root
+    let leaves = n + (arity - 1 - (n - 1) % (arity - 1)) % (arity - 1);
+    let nodes = leaves + (leaves - 1) / (arity - 1);
+
+    let mut node_weights = [0; MAX_NODES];
+    let mut parents = [0; MAX_NODES];
+    let mut active = [false; MAX_NODES];
+    node_weights[..n].copy_from_slice(weights);
+    for a in active.iter_mut().take(leaves) {
+        *a = true;
+    }
+
+    for node in leaves..nodes {
+        for _ in 0..arity {
+            // Ties go to the lowest index so leaves are merged before internal nodes
+            let min = (0..node)
+                .filter(|i| active[*i])
+                .min_by_key(|i| node_weights[*i])
+                .unwrap();
+            active[min] = false;
+            parents[min] = node;
+            node_weights[node] += node_weights[min];
+        }
+        active[node] = true;
+    }
+
+    for (symbol, length) in lengths.iter_mut().enumerate().take(n) {
+        *length = 0;
+        let mut node = symbol;
+        while node != nodes - 1 {
+            node = parents[node];
+            *length += 1;
+        }
+    }
+}
+
+/// Canonical prefix code over `N` symbols using zero-width characters as digits
+///
+/// Symbols with a length of 0 are absent from the code.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct Canonical<const N: usize> {
+    lengths: [u8; N],
+    codewords: [[u8; MAX_LEN]; N],
+    /// Longest codeword
+    max: usize,
+    /// Value of the first codeword of each length
+    first: [usize; MAX_LEN + 1],
+    /// Number of codewords of each length
+    count: [usize; MAX_LEN + 1],
+    /// Index of the first symbol of each length in `symbols`
+    offset: [usize; MAX_LEN + 1],
+    /// Symbols sorted by codeword
+    symbols: [u8; N],
+}
+impl<const N: usize> Canonical<N> {
+    /// Creates a canonical code from the codeword length of each symbol
+    pub(crate) fn from_lengths(lengths: [u8; N]) -> Result<Self, Error> {
+        let mut code = Self {
+            lengths,
+            codewords: [[0; MAX_LEN]; N],
+            max: 0,
+            first: [0; MAX_LEN + 1],
+            count: [0; MAX_LEN + 1],
+            offset: [0; MAX_LEN + 1],
+            symbols: [0; N],
+        };
+        if lengths.iter().any(|l| *l as usize > MAX_LEN) {
+            return Err(Error::InvalidCodeLengths);
+        }
+
+        let mut value = 0;
+        let mut index = 0;
+        for len in 1..=MAX_LEN {
+            value *= CHARS.len();
+            code.first[len] = value;
+            code.offset[len] = index;
+
+            for symbol in (0..N).filter(|s| lengths[*s] as usize == len) {
+                if value >= CHARS.len().pow(len as u32) {
+                    return Err(Error::InvalidCodeLengths);
+                }
+
+                let mut v = value;
+                for d in code.codewords[symbol][..len].iter_mut().rev() {
+                    *d = (v % CHARS.len()) as u8;
+                    v /= CHARS.len();
+                }
+                code.symbols[index] = symbol as u8;
+                code.count[len] += 1;
+                code.max = len;
+                value += 1;
+                index += 1;
+            }
+        }
+
+        Ok(code)
+    }
+
+    /// Returns the codeword length of each symbol
+    #[inline]
+    pub(crate) fn lengths(&self) -> [u8; N] {
+        self.lengths
+    }
+
+    /// Returns the codeword length of a symbol
+    #[inline]
+    pub(crate) fn len(&self, symbol: u8) -> usize {
+        self.lengths[symbol as usize] as usize
+    }
+
+    /// Writes the codeword of a symbol to a buffer and returns its length
+    #[inline]
+    pub(crate) fn write_codeword(&self, symbol: u8, out: &mut [char]) -> usize {
+        let len = self.len(symbol);
+        for (dst, d) in out.iter_mut().zip(&self.codewords[symbol as usize][..len]) {
+            *dst = CHARS[*d as usize];
+        }
+        len
+    }
+
+    /// Reads a symbol from a character iterator, reporting `read` characters in the current block on error
+    #[inline]
+    pub(crate) fn read_symbol<T: Iterator<Item = char>>(
+        &self,
+        iter: &mut T,
+        read: &mut usize,
+    ) -> Option<Result<u8, Error>> {
+        let mut value = 0;
+        for len in 1..=MAX_LEN {
+            let c = match iter.next() {
+                Some(c) => c,
+                None if *read == 0 => return None,
+                None => return Some(Err(Error::IncompleteBlock(*read))),
+            };
+            *read += 1;
+            value = value * CHARS.len()
+                + match digit(c) {
+                    Ok(d) => d,
+                    Err(e) => return Some(Err(e)),
+                };
+
+            if value >= self.first[len] && value - self.first[len] < self.count[len] {
+                return Some(Ok(self.symbols[self.offset[len] + value - self.first[len]]));
+            }
+            if len >= self.max {
+                return Some(Err(Error::InvalidCharacter(c)));
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// Represents a variable-length code mapping every 4 bits pattern to 1 to 3 zero-width characters
+///
+/// Codes are canonical, so they can be fully described by their lengths.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NibbleCode(Canonical<NIBBLES>);
+impl NibbleCode {
+    /// Creates a canonical code from the codeword length of each 4 bits pattern
+    pub fn from_lengths(lengths: [u8; NIBBLES]) -> Result<Self, Error> {
+        if lengths
+            .iter()
+            .any(|l| *l == 0 || *l as usize > MAX_NIBBLE_LEN)
+        {
+            return Err(Error::InvalidCodeLengths);
+        }
+        Canonical::from_lengths(lengths).map(Self)
+    }
+
+    /// Creates the optimal code for the provided data and returns it along with the encoded length in characters
+    pub fn optimal(data: &[u8]) -> (Self, usize) {
+        let mut frequencies = [0; NIBBLES];
+        for b in data.iter().copied() {
+            frequencies[(b & 0b0000_1111) as usize] += 1;
+            frequencies[((b & 0b1111_0000) >> 4) as usize] += 1;
+        }
+
+        // With 16 symbols no dummy leaves are required and there are exactly 3 merges,
+        // so no codeword is longer than 3 characters
+        let mut lengths = [0; NIBBLES];
+        huffman_lengths(&frequencies, &mut lengths);
+        let code = Self::from_lengths(lengths).unwrap();
+        let len = frequencies
+            .iter()
+            .zip(lengths.iter())
+            .map(|(f, l)| f * *l as usize)
+            .sum();
+        (code, len)
+    }
+
+    /// Returns the codeword length of each 4 bits pattern
+    pub fn lengths(&self) -> [u8; NIBBLES] {
+        self.0.lengths()
+    }
+
+    /// Returns the number of zero-width characters required to encode the provided data
+    pub fn encoded_len(&self, data: &[u8]) -> usize {
+        data.iter()
+            .map(|b| self.0.len(b & 0b0000_1111) + self.0.len(b >> 4))
+            .sum()
+    }
+}
+
+/// Converts a byte iterator into a zero-width character iterator using the provided variable-length code
+pub fn encode_huffman<T: Iterator<Item = u8>>(iter: T, code: NibbleCode) -> EncodeHuffmanIter<T> {
+    EncodeHuffmanIter::new(iter, code)
+}
+
+/// Converts a zero-width character iterator encoded using the provided variable-length code into a byte iterator
+pub fn decode_huffman<T: Iterator<Item = char>>(iter: T, code: NibbleCode) -> DecodeHuffmanIter<T> {
+    DecodeHuffmanIter::new(iter, code)
+}
+
+/// Iterator converting bytes into zero-width characters using a variable-length code, returned by [`encode_huffman`]
+#[derive(Debug, Clone)]
+pub struct EncodeHuffmanIter<T: Iterator<Item = u8>> {
+    inner: T,
+    code: NibbleCode,
+    buffer: [char; MAX_NIBBLE_LEN * 2],
+    buffer_len: usize,
+    cursor: usize,
+}
+impl<T: Iterator<Item = u8>> EncodeHuffmanIter<T> {
+    /// Creates an encoding iterator over the provided bytes using the provided variable-length code
+    pub fn new(inner: T, code: NibbleCode) -> Self {
+        Self {
+            inner,
+            code,
+            buffer: ['\0'; MAX_NIBBLE_LEN * 2],
+            buffer_len: 0,
+            cursor: 0,
+        }
+    }
+}
+impl<T: Iterator<Item = u8>> Iterator for EncodeHuffmanIter<T> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor == self.buffer_len {
+            let b = self.inner.next()?;
+            let len = self
+                .code
+                .0
+                .write_codeword(b & 0b0000_1111, &mut self.buffer);
+            self.buffer_len = len + self.code.0.write_codeword(b >> 4, &mut self.buffer[len..]);
+            self.cursor = 0;
+        }
+
+        let ret = Some(self.buffer[self.cursor]);
+        self.cursor += 1;
+        ret
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Every byte takes between the shortest and longest codewords twice
+        let lengths = self.code.lengths();
+        let shortest = *lengths.iter().min().unwrap() as usize * 2;
+        let longest = *lengths.iter().max().unwrap() as usize * 2;
+
+        let buffered = self.buffer_len - self.cursor;
+        let (lower, upper) = self.inner.size_hint();
+        (
+            lower.saturating_mul(shortest).saturating_add(buffered),
+            upper.and_then(|u| u.checked_mul(longest)?.checked_add(buffered)),
+        )
+    }
+}
+impl<T: FusedIterator<Item = u8>> FusedIterator for EncodeHuffmanIter<T> {}
+
+/// Iterator converting zero-width characters encoded using a variable-length code into bytes,
+/// returned by [`decode_huffman`]
+#[derive(Debug, Clone)]
+pub struct DecodeHuffmanIter<T: Iterator<Item = char>> {
+    inner: T,
+    code: NibbleCode,
+}
+impl<T: Iterator<Item = char>> DecodeHuffmanIter<T> {
+    /// Creates a decoding iterator over the provided characters using the provided variable-length code
+    pub fn new(inner: T, code: NibbleCode) -> Self {
+        Self { inner, code }
+    }
+}
+impl<T: Iterator<Item = char>> Iterator for DecodeHuffmanIter<T> {
+    type Item = Result<u8, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut read = 0;
+        let low = match self.code.0.read_symbol(&mut self.inner, &mut read)? {
+            Ok(p) => p,
+            Err(e) => return Some(Err(e)),
+        };
+        let high = match self.code.0.read_symbol(&mut self.inner, &mut read) {
+            Some(Ok(p)) => p,
+            Some(Err(e)) => return Some(Err(e)),
+            None => return Some(Err(Error::IncompleteBlock(read))),
+        };
+        Some(Ok(low | (high << 4)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EncodeHuffmanIter;
+    use crate::tests::assert_size_hints;
+
+    static SRC: &[u8] = include_bytes!("./huffman.rs");
+
+    #[test]
+    fn huffman_round_trip() {
+        let (code, len) = crate::NibbleCode::optimal(SRC);
+        assert_eq!(len, code.encoded_len(SRC));
+        assert_eq!(
+            len,
+            crate::encode_huffman(SRC.iter().copied(), code).count()
+        );
+
+        let encoded = crate::encode_huffman(SRC.iter().copied(), code);
+        let decoded = crate::decode_huffman(encoded, code);
+        for (ob, db) in SRC.iter().copied().zip(decoded) {
+            assert_eq!(ob, db.unwrap());
+        }
+    }
+
+    #[test]
+    fn huffman_size_hints() {
+        for data in [SRC, &[0; 64][..], b""].iter() {
+            let (code, len) = crate::NibbleCode::optimal(data);
+            assert_size_hints(EncodeHuffmanIter::new(data.iter().copied(), code), len);
+        }
+    }
+
+    #[test]
+    fn huffman_beats_compression() {
+        let (compression, ..) = crate::Compression::optimal(SRC);
+        let (_, len) = crate::NibbleCode::optimal(SRC);
+        assert!(len <= crate::encoded_compress_len(SRC, compression));
+
+        // Skewed data favours short codewords for the most common patterns
+        let (code, len) = crate::NibbleCode::optimal(&[0; 64]);
+        assert_eq!(code.lengths()[0], 1);
+        assert_eq!(len, 128);
+    }
+
+    #[test]
+    fn invalid_code_lengths() {
+        assert_eq!(
+            crate::NibbleCode::from_lengths([1; 16]),
+            Err(crate::Error::InvalidCodeLengths)
+        );
+        assert_eq!(
+            crate::NibbleCode::from_lengths([0; 16]),
+            Err(crate::Error::InvalidCodeLengths)
+        );
+
+        let code = crate::NibbleCode::from_lengths([3; 16]).unwrap();
+        let chars = [crate::CHARS[1], crate::CHARS[0], crate::CHARS[5]];
+        let mut decoded = crate::decode_huffman(chars.iter().copied(), code);
+        assert_eq!(
+            decoded.next(),
+            Some(Err(crate::Error::InvalidCharacter(crate::CHARS[5])))
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use crate::Compression;
+use std::io::{self, Read, Write};
+
+/// Maximum number of bytes encoded or decoded at once
+const CHUNK_LEN: usize = 8 * 1024;
+
+/// Wraps a crate error into an IO error
+pub(crate) fn invalid_data(e: crate::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Writer encoding written bytes as UTF-8 zero-width characters into an underlying writer
+#[derive(Debug)]
+pub struct ZwEncoder<W: Write> {
+    inner: W,
+    compression: Option<Compression>,
+    buffer: Vec<u8>,
+}
+impl<W: Write> ZwEncoder<W> {
+    /// Creates an encoder writing to the provided writer without compression
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            compression: None,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Creates an encoder writing to the provided writer compressed using the provided settings
+    pub fn with_compression(inner: W, compression: Compression) -> Self {
+        Self {
+            inner,
+            compression: Some(compression),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying writer
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+impl<W: Write> Write for ZwEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = encode_chunk(buf, &mut self.buffer, self.compression)?;
+        self.inner.write_all(&self.buffer)?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader decoding UTF-8 zero-width characters read from an underlying reader
+#[derive(Debug)]
+pub struct ZwDecoder<R: Read> {
+    inner: R,
+    state: DecodeState,
+}
+impl<R: Read> ZwDecoder<R> {
+    /// Creates a decoder reading from the provided reader without compression
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: DecodeState::new(None),
+        }
+    }
+
+    /// Creates a decoder reading from the provided reader decompressed using the provided settings
+    pub fn with_compression(inner: R, compression: Compression) -> Self {
+        Self {
+            inner,
+            state: DecodeState::new(Some(compression)),
+        }
+    }
+
+    /// Returns a reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the underlying reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+impl<R: Read> Read for ZwDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !self.state.has_output() && !self.state.is_eof() {
+            let read = self.inner.read(self.state.unfilled())?;
+            self.state.filled(read)?;
+        }
+        Ok(self.state.read_output(buf))
+    }
+}
+
+/// Encodes at most a chunk of data into a buffer as UTF-8 and returns the number of bytes consumed
+pub(crate) fn encode_chunk(
+    data: &[u8],
+    buffer: &mut Vec<u8>,
+    compression: Option<Compression>,
+) -> io::Result<usize> {
+    let data = &data[..data.len().min(CHUNK_LEN)];
+    buffer.resize(crate::encoded_str_len(data.len()), 0);
+
+    let len = match compression {
+        Some(compression) => crate::encode_compress_to_str_buf(data, buffer, compression),
+        None => crate::encode_to_str_buf(data, buffer),
+    }
+    .map_err(invalid_data)?
+    .len();
+    buffer.truncate(len);
+
+    Ok(data.len())
+}
+
+/// Decodes the complete blocks of UTF-8 input, or all of it at the end of the input,
+/// into a buffer and returns the number of bytes consumed
+pub(crate) fn decode_chunk(
+    input: &[u8],
+    eof: bool,
+    output: &mut Vec<u8>,
+    compression: Option<Compression>,
+) -> io::Result<usize> {
+    // Only decode complete blocks, keeping sequences split across reads for later
+    let len = if eof {
+        input.len()
+    } else {
+        complete_len(input, compression)
+    };
+    let s = std::str::from_utf8(&input[..len])
+        .map_err(|e| invalid_data(crate::Error::InvalidByte(input[e.valid_up_to()])))?;
+
+    let start = output.len();
+    output.resize(start + len / 6, 0);
+    let decoded = match compression {
+        Some(compression) => {
+            crate::decode_decompress_from_str(s, &mut output[start..], compression)
+        }
+        None => crate::decode_from_str(s, &mut output[start..]),
+    }
+    .map_err(invalid_data)?;
+    output.truncate(start + decoded);
+
+    Ok(len)
+}
+
+/// Decoding state shared by the blocking and asynchronous decoders
+#[derive(Debug)]
+pub(crate) struct DecodeState {
+    compression: Option<Compression>,
+    input: Vec<u8>,
+    input_len: usize,
+    output: Vec<u8>,
+    cursor: usize,
+    eof: bool,
+}
+impl DecodeState {
+    pub(crate) fn new(compression: Option<Compression>) -> Self {
+        Self {
+            compression,
+            input: Vec::new(),
+            input_len: 0,
+            output: Vec::new(),
+            cursor: 0,
+            eof: false,
+        }
+    }
+
+    /// Returns whether decoded bytes are waiting to be read
+    pub(crate) fn has_output(&self) -> bool {
+        self.cursor < self.output.len()
+    }
+
+    /// Returns whether the end of the input was reached
+    pub(crate) fn is_eof(&self) -> bool {
+        self.eof
+    }
+
+    /// Returns the part of the input buffer new input should be read into
+    pub(crate) fn unfilled(&mut self) -> &mut [u8] {
+        self.input.resize(crate::encoded_str_len(CHUNK_LEN), 0);
+        &mut self.input[self.input_len..]
+    }
+
+    /// Decodes as much of the input as possible after `read` new bytes were read into the input buffer
+    pub(crate) fn filled(&mut self, read: usize) -> io::Result<()> {
+        self.input_len += read;
+        self.eof = read == 0;
+
+        self.output.clear();
+        self.cursor = 0;
+        let len = decode_chunk(
+            &self.input[..self.input_len],
+            self.eof,
+            &mut self.output,
+            self.compression,
+        )?;
+
+        self.input.copy_within(len..self.input_len, 0);
+        self.input_len -= len;
+        Ok(())
+    }
+
+    /// Copies decoded bytes into a buffer and returns the number of bytes copied
+    pub(crate) fn read_output(&mut self, buf: &mut [u8]) -> usize {
+        let available = &self.output[self.cursor..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.cursor += len;
+        len
+    }
+}
+
+/// Returns the length of the longest prefix made of complete blocks
+fn complete_len(data: &[u8], compression: Option<Compression>) -> usize {
+    if compression.is_none() {
+        return data.len() - (data.len() % 12);
+    }
+
+    let mut len = 0;
+    let mut cursor = 0;
+    let mut nibbles = 0;
+    while cursor + 3 <= data.len() {
+        cursor += match data[cursor..cursor + 3] {
+            // Compressed nibbles only take a single character
+            [0xE2, 0x81, 0xA3] | [0xE2, 0x81, 0xA4] => 3,
+            _ if cursor + 6 <= data.len() => 6,
+            _ => break,
+        };
+        nibbles += 1;
+        if nibbles % 2 == 0 {
+            len = cursor;
+        }
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    static SRC: &[u8] = include_bytes!("./io.rs");
+
+    /// Reader returning at most 7 bytes at a time to split characters across reads
+    struct Trickle<'a>(&'a [u8]);
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = self.0.len().min(buf.len()).min(7);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn io_round_trip() {
+        let mut encoder = crate::ZwEncoder::new(Vec::new());
+        encoder.write_all(SRC).unwrap();
+        let encoded = encoder.into_inner();
+        assert!(std::str::from_utf8(&encoded)
+            .unwrap()
+            .chars()
+            .eq(crate::encode(SRC.iter().copied())));
+
+        let mut decoded = Vec::new();
+        crate::ZwDecoder::new(Trickle(&encoded))
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(SRC, decoded.as_slice());
+    }
+
+    #[test]
+    fn compression_io_round_trip() {
+        let (compression, ..) = crate::Compression::optimal(SRC);
+        let mut encoder = crate::ZwEncoder::with_compression(Vec::new(), compression);
+        encoder.write_all(SRC).unwrap();
+        let encoded = encoder.into_inner();
+
+        let mut decoded = Vec::new();
+        crate::ZwDecoder::with_compression(Trickle(&encoded), compression)
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(SRC, decoded.as_slice());
+    }
+
+    #[test]
+    fn io_incomplete() {
+        let encoded: Vec<u8> = crate::encode_utf8(b"ab".iter().copied()).collect();
+        let mut decoded = Vec::new();
+        let err = crate::ZwDecoder::new(&encoded[..encoded.len() - 3])
+            .read_to_end(&mut decoded)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
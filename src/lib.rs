@@ -1,9 +1,58 @@
-#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-#[cfg(feature = "camo")]
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub use adaptive::*;
+#[cfg(any(feature = "async", feature = "futures-io"))]
+pub use async_io::*;
+#[cfg(feature = "camo-core")]
 pub use camo::*;
+pub use entropy::*;
+pub use ext::*;
+pub use huffman::*;
+#[cfg(feature = "std")]
+pub use io::*;
+pub use slice::*;
+#[cfg(all(feature = "camo-core", feature = "std"))]
+pub use stream::*;
+pub use utf8::*;
+
+mod adaptive;
+#[cfg(any(feature = "async", feature = "futures-io"))]
+mod async_io;
+mod entropy;
+mod ext;
+#[cfg(feature = "fingerprint")]
+pub mod fingerprint;
+#[cfg(any(
+    feature = "email",
+    feature = "html",
+    feature = "json",
+    feature = "markdown",
+    feature = "office",
+    feature = "source",
+    feature = "toml",
+    feature = "yaml"
+))]
+pub mod formats;
+#[cfg(feature = "camo-core")]
+mod heap;
+mod huffman;
+#[cfg(feature = "std")]
+mod io;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "sign")]
+pub mod sign;
+mod simd;
+mod slice;
+#[cfg(all(feature = "camo-core", feature = "std"))]
+mod stream;
+mod utf8;
 
 use core::fmt;
+use core::iter::FusedIterator;
 
 /// Zero-width characters
 pub const CHARS: [char; 6] = [
@@ -99,6 +148,9 @@ impl Compression {
     }
 
     /// Creates a compression setting by finding the two most common patterns in the provided data
+    ///
+    /// The resulting length can be computed using [`encoded_compress_len`],
+    /// and [`NibbleCode::optimal`] can be used for a variable-length code which is never longer.
     pub fn optimal(data: &[u8]) -> (Self, u8, u8) {
         let mut patterns: [usize; 16] = [0; 16];
         for b in data.iter().copied() {
@@ -106,17 +158,22 @@ impl Compression {
             patterns[((b & 0b1111_0000) >> 4) as usize] += 1;
         }
 
-        let recurrent_patterns = patterns.iter().enumerate().fold((0, 0), |acc, (i, o)| {
-            if *o > patterns[acc.0] {
-                (i, acc.1)
-            } else if *o > patterns[acc.1] {
-                (acc.0, i)
-            } else {
-                acc
+        // Keep track of the two most recurrent patterns, demoting the best one when it is beaten
+        let (mut rp0, mut rp1) = if patterns[1] > patterns[0] {
+            (1, 0)
+        } else {
+            (0, 1)
+        };
+        for (i, o) in patterns.iter().enumerate().skip(2) {
+            if *o > patterns[rp0] {
+                rp1 = rp0;
+                rp0 = i;
+            } else if *o > patterns[rp1] {
+                rp1 = i;
             }
-        });
-        let rp0 = recurrent_patterns.0 as u8;
-        let rp1 = recurrent_patterns.1 as u8;
+        }
+        let rp0 = rp0 as u8;
+        let rp1 = rp1 as u8;
 
         (Self::new(rp0, rp1).unwrap(), rp0, rp1)
     }
@@ -216,50 +273,34 @@ impl Compression {
 }
 
 /// Converts a byte iterator into a zero-width character iterator
-pub fn encode<T: Iterator<Item = u8>>(iter: T) -> impl Iterator<Item = char> {
-    EncodeIter {
-        inner: iter,
-        buffer: ['\0'; 4],
-        cursor: 0,
-    }
+pub fn encode<T: Iterator<Item = u8>>(iter: T) -> EncodeIter<T> {
+    EncodeIter::new(iter)
 }
 
 /// Converts a zero-width character iterator into a byte iterator
-pub fn decode<T: Iterator<Item = char>>(iter: T) -> impl Iterator<Item = Result<u8, Error>> {
-    DecodeIter { inner: iter }
+pub fn decode<T: Iterator<Item = char>>(iter: T) -> DecodeIter<T> {
+    DecodeIter::new(iter)
 }
 
 /// Converts a byte iterator into a zero-width character iterator compressed using the provided settings
 pub fn encode_compress<T: Iterator<Item = u8>>(
     iter: T,
     compression: Compression,
-) -> impl Iterator<Item = char> {
-    EncodeCompressIter {
-        inner: iter,
-        buffer: ['\0'; 4],
-        buffer_len: 0,
-        compression,
-        cursor: 0,
-    }
+) -> EncodeCompressIter<T> {
+    EncodeCompressIter::new(iter, compression)
 }
 
 /// Converts a zero-width character iterator into a byte iterator decompressed using the provided settings
 pub fn decode_decompress<T: Iterator<Item = char>>(
     iter: T,
     compression: Compression,
-) -> impl Iterator<Item = Result<u8, Error>> {
-    DecodeDecompressIter {
-        inner: iter,
-        compression,
-    }
+) -> DecodeDecompressIter<T> {
+    DecodeDecompressIter::new(iter, compression)
 }
 
 /// Check if a character is zero-width
 pub fn is_zw(c: char) -> bool {
-    match c {
-        CHAR0 | CHAR1 | CHAR2 | CHAR3 | CHAR4 | CHAR5 => true,
-        _ => false,
-    }
+    matches!(c, CHAR0 | CHAR1 | CHAR2 | CHAR3 | CHAR4 | CHAR5)
 }
 
 /// Represents an error that might occur while dealing with zero-width character iterators
@@ -271,6 +312,12 @@ pub enum Error {
     IncompleteBlock(usize),
     /// Occurs when trying to use a pattern larger than 4 bits for compression
     InvalidCompressionPattern(u8),
+    /// Occurs when trying to decode a byte that isn't part of a UTF-8 encoded zero-width character
+    InvalidByte(u8),
+    /// Occurs when a caller-provided buffer is too small, contains the required length
+    BufferTooSmall(usize),
+    /// Occurs when trying to build a variable-length code from lengths which don't form a valid prefix code
+    InvalidCodeLengths,
 }
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -282,47 +329,142 @@ impl fmt::Display for Error {
             Self::InvalidCompressionPattern(p) => {
                 write!(f, "expected a 4 bits value but got {:08b}", p)
             }
+            Self::InvalidByte(b) => {
+                write!(
+                    f,
+                    "expected UTF-8 encoded zero-width character but got {:#04X}",
+                    b
+                )
+            }
+            Self::BufferTooSmall(len) => {
+                write!(f, "expected a buffer of at least {} elements", len)
+            }
+            Self::InvalidCodeLengths => write!(f, "code lengths don't form a valid prefix code"),
         }
     }
 }
 #[cfg(feature = "std")]
 impl std::error::Error for crate::Error {}
 
-/// Encoding iterator
-struct EncodeIter<T: Iterator<Item = u8>> {
+/// Buffered items of a single block, consumed from both ends
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct BlockBuffer<T: Copy, const N: usize> {
+    items: [T; N],
+    start: usize,
+    end: usize,
+}
+impl<T: Copy, const N: usize> BlockBuffer<T, N> {
+    /// Creates a buffer from the first `len` items
+    #[inline]
+    pub(crate) fn new(items: [T; N], len: usize) -> Self {
+        Self {
+            items,
+            start: 0,
+            end: len,
+        }
+    }
+
+    /// Creates an empty buffer
+    #[inline]
+    pub(crate) fn empty(fill: T) -> Self {
+        Self::new([fill; N], 0)
+    }
+
+    /// Returns the number of items left
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Takes the first item left
+    #[inline]
+    pub(crate) fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        self.start += 1;
+        Some(self.items[self.start - 1])
+    }
+
+    /// Takes the last item left
+    #[inline]
+    pub(crate) fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(self.items[self.end])
+    }
+}
+
+/// Iterator converting bytes into zero-width characters, returned by [`encode`]
+#[derive(Debug, Clone)]
+pub struct EncodeIter<T: Iterator<Item = u8>> {
     inner: T,
-    buffer: [char; 4],
-    cursor: usize,
+    front: BlockBuffer<char, 4>,
+    back: BlockBuffer<char, 4>,
+}
+impl<T: Iterator<Item = u8>> EncodeIter<T> {
+    /// Creates an encoding iterator over the provided bytes
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            front: BlockBuffer::empty('\0'),
+            back: BlockBuffer::empty('\0'),
+        }
+    }
 }
 impl<T: Iterator<Item = u8>> Iterator for EncodeIter<T> {
     type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.cursor == 0 {
-            self.buffer = match self.inner.next() {
-                Some(b) => Block::from(b).to_chars(),
-                None => return None,
-            };
+        loop {
+            if let Some(c) = self.front.next() {
+                return Some(c);
+            }
+            match self.inner.next() {
+                Some(b) => self.front = BlockBuffer::new(Block::from(b).to_chars(), 4),
+                None => return self.back.next(),
+            }
         }
-
-        let ret = Some(self.buffer[self.cursor]);
-        self.cursor = (self.cursor + 1) % 4;
-        ret
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let inner_size_hint = self.inner.size_hint();
+        let buffered = self.front.len() + self.back.len();
+        let (lower, upper) = self.inner.size_hint();
         (
-            (inner_size_hint.0 * 8) + (3 - self.cursor),
-            inner_size_hint.1.map(|b| (b * 8) + (3 - self.cursor)),
+            lower.saturating_mul(4).saturating_add(buffered),
+            upper.and_then(|u| u.checked_mul(4)?.checked_add(buffered)),
         )
     }
 }
+impl<T: DoubleEndedIterator<Item = u8>> DoubleEndedIterator for EncodeIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(c) = self.back.next_back() {
+                return Some(c);
+            }
+            match self.inner.next_back() {
+                Some(b) => self.back = BlockBuffer::new(Block::from(b).to_chars(), 4),
+                None => return self.front.next_back(),
+            }
+        }
+    }
+}
+impl<T: ExactSizeIterator<Item = u8>> ExactSizeIterator for EncodeIter<T> {}
+impl<T: FusedIterator<Item = u8>> FusedIterator for EncodeIter<T> {}
 
-/// Decoding iterator
-struct DecodeIter<T: Iterator<Item = char>> {
+/// Iterator converting zero-width characters into bytes, returned by [`decode`]
+#[derive(Debug, Clone)]
+pub struct DecodeIter<T: Iterator<Item = char>> {
     inner: T,
 }
+impl<T: Iterator<Item = char>> DecodeIter<T> {
+    /// Creates a decoding iterator over the provided characters
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
 impl<T: Iterator<Item = char>> Iterator for DecodeIter<T> {
     type Item = Result<u8, Error>;
 
@@ -344,53 +486,111 @@ impl<T: Iterator<Item = char>> Iterator for DecodeIter<T> {
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let inner_size_hint = self.inner.size_hint();
-        (inner_size_hint.0 / 8, inner_size_hint.1.map(|b| b / 8))
+        // Every block of 4 characters yields a byte, and an incomplete block at the end yields an error
+        let (lower, upper) = self.inner.size_hint();
+        (lower.div_ceil(4), upper.map(|u| u.div_ceil(4)))
     }
 }
+impl<T: DoubleEndedIterator<Item = char> + ExactSizeIterator> DoubleEndedIterator
+    for DecodeIter<T>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        // An incomplete block can only be at the end, in which case it is yielded first
+        let remainder = self.inner.len() % 4;
+        if remainder != 0 {
+            for _ in 0..remainder {
+                self.inner.next_back();
+            }
+            return Some(Err(Error::IncompleteBlock(remainder)));
+        }
+
+        let c3 = self.inner.next_back()?;
+        let c2 = self.inner.next_back()?;
+        let c1 = self.inner.next_back()?;
+        let c0 = self.inner.next_back()?;
+        Some(Block::from_chars([c0, c1, c2, c3]).map(Into::into))
+    }
+}
+impl<T: ExactSizeIterator<Item = char>> ExactSizeIterator for DecodeIter<T> {}
+impl<T: FusedIterator<Item = char>> FusedIterator for DecodeIter<T> {}
 
-/// Encoding and compressing iterator
-struct EncodeCompressIter<T: Iterator<Item = u8>> {
+/// Iterator converting bytes into compressed zero-width characters, returned by [`encode_compress`]
+#[derive(Debug, Clone)]
+pub struct EncodeCompressIter<T: Iterator<Item = u8>> {
     inner: T,
     compression: Compression,
-    buffer: [char; 4],
-    buffer_len: usize,
-    cursor: usize,
+    front: BlockBuffer<char, 4>,
+    back: BlockBuffer<char, 4>,
+}
+impl<T: Iterator<Item = u8>> EncodeCompressIter<T> {
+    /// Creates an encoding iterator over the provided bytes compressed using the provided settings
+    pub fn new(inner: T, compression: Compression) -> Self {
+        Self {
+            inner,
+            compression,
+            front: BlockBuffer::empty('\0'),
+            back: BlockBuffer::empty('\0'),
+        }
+    }
 }
 impl<T: Iterator<Item = u8>> Iterator for EncodeCompressIter<T> {
     type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.cursor == 0 {
-            let nb = match self.inner.next() {
-                Some(b) => Block::from(b).to_compressed_chars(self.compression),
-                None => return None,
-            };
-            self.buffer = nb.0;
-            self.buffer_len = nb.1;
+        loop {
+            if let Some(c) = self.front.next() {
+                return Some(c);
+            }
+            match self.inner.next() {
+                Some(b) => {
+                    let (chars, len) = Block::from(b).to_compressed_chars(self.compression);
+                    self.front = BlockBuffer::new(chars, len);
+                }
+                None => return self.back.next(),
+            }
         }
-
-        let ret = Some(self.buffer[self.cursor]);
-        self.cursor = (self.cursor + 1) % self.buffer_len;
-        ret
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let inner_size_hint = self.inner.size_hint();
+        // Every byte takes between 2 and 4 characters depending on its patterns
+        let buffered = self.front.len() + self.back.len();
+        let (lower, upper) = self.inner.size_hint();
         (
-            (inner_size_hint.0 * 4) + (self.buffer_len - 1 - self.cursor),
-            inner_size_hint
-                .1
-                .map(|b| (b * 8) + (self.buffer_len - 1 - self.cursor)),
+            lower.saturating_mul(2).saturating_add(buffered),
+            upper.and_then(|u| u.checked_mul(4)?.checked_add(buffered)),
         )
     }
 }
+impl<T: DoubleEndedIterator<Item = u8>> DoubleEndedIterator for EncodeCompressIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(c) = self.back.next_back() {
+                return Some(c);
+            }
+            match self.inner.next_back() {
+                Some(b) => {
+                    let (chars, len) = Block::from(b).to_compressed_chars(self.compression);
+                    self.back = BlockBuffer::new(chars, len);
+                }
+                None => return self.front.next_back(),
+            }
+        }
+    }
+}
+impl<T: FusedIterator<Item = u8>> FusedIterator for EncodeCompressIter<T> {}
 
-/// Decoding and decompressing iterator
-struct DecodeDecompressIter<T: Iterator<Item = char>> {
+/// Iterator converting compressed zero-width characters into bytes, returned by [`decode_decompress`]
+#[derive(Debug, Clone)]
+pub struct DecodeDecompressIter<T: Iterator<Item = char>> {
     inner: T,
     compression: Compression,
 }
+impl<T: Iterator<Item = char>> DecodeDecompressIter<T> {
+    /// Creates a decoding iterator over the provided characters decompressed using the provided settings
+    pub fn new(inner: T, compression: Compression) -> Self {
+        Self { inner, compression }
+    }
+}
 impl<T: Iterator<Item = char>> Iterator for DecodeDecompressIter<T> {
     type Item = Result<u8, Error>;
 
@@ -427,85 +627,235 @@ impl<T: Iterator<Item = char>> Iterator for DecodeDecompressIter<T> {
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let inner_size_hint = self.inner.size_hint();
-        (inner_size_hint.0 / 4, inner_size_hint.1.map(|b| b / 8))
+        // Every byte takes between 2 and 4 characters, and an incomplete block at the end
+        // can yield an error after a single one
+        let (lower, upper) = self.inner.size_hint();
+        (lower.div_ceil(4), upper.map(|u| u.div_ceil(2)))
     }
 }
+impl<T: FusedIterator<Item = char>> FusedIterator for DecodeDecompressIter<T> {}
 
-#[cfg(feature = "camo")]
+#[cfg(feature = "camo-core")]
 mod camo {
-    use std::fmt;
+    pub use crate::heap::BrotliError;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::fmt;
+    use rand_core::{CryptoRng, RngCore};
+
+    /// Length of the digest of the visible text appended to payloads bound to it
+    const COVER_DIGEST_LEN: usize = 16;
+    /// Character following the compression pattern of payloads bound to their visible text
+    const BOUND: char = crate::CHARS[4];
 
     /// Hides a compressed and optionally encrypted payload inside a string
+    #[cfg(feature = "std")]
     pub fn camouflage(
         payload: Vec<u8>,
         dummy: &str,
         key: Option<&str>,
         compression_level: Option<i32>,
     ) -> Result<String, Error> {
-        use brotli::enc::BrotliEncoderParams;
         use chacha20::ChaCha20Rng;
+        use rand_core::SeedableRng;
+
+        camouflage_with_rng(
+            payload,
+            dummy,
+            key,
+            compression_level,
+            &mut ChaCha20Rng::from_entropy(),
+        )
+    }
+
+    /// Hides a compressed and optionally encrypted payload inside a string,
+    /// generating the encryption nonce using the provided random number generator
+    pub fn camouflage_with_rng<R: RngCore + CryptoRng>(
+        payload: Vec<u8>,
+        dummy: &str,
+        key: Option<&str>,
+        compression_level: Option<i32>,
+        rng: &mut R,
+    ) -> Result<String, Error> {
+        hide(payload, dummy, key, compression_level, false, rng)
+    }
+
+    /// Hides a compressed and optionally encrypted payload inside a string, bound to its visible text
+    ///
+    /// [`decamouflage`] fails with [`Error::CoverMismatch`] if the hidden data is moved to another string
+    /// or if the visible text is edited. Zero-width characters are ignored, and so is leading and trailing whitespace
+    /// so that the line endings added when saving or pasting the string don't unbind it. Edits to that whitespace
+    /// are therefore not detected.
+    ///
+    /// Only keyed binding resists tampering, since the visible text is then authenticated along with the payload.
+    /// Without a key, the payload is followed by a plain digest of the visible text and by a marker, which anyone
+    /// can strip or recompute, so keyless binding only detects accidental edits.
+    #[cfg(feature = "std")]
+    pub fn camouflage_bound(
+        payload: Vec<u8>,
+        dummy: &str,
+        key: Option<&str>,
+        compression_level: Option<i32>,
+    ) -> Result<String, Error> {
+        use chacha20::ChaCha20Rng;
+        use rand_core::SeedableRng;
+
+        camouflage_bound_with_rng(
+            payload,
+            dummy,
+            key,
+            compression_level,
+            &mut ChaCha20Rng::from_entropy(),
+        )
+    }
+
+    /// Hides a compressed and optionally encrypted payload inside a string, bound to its visible text,
+    /// generating the encryption nonce using the provided random number generator
+    pub fn camouflage_bound_with_rng<R: RngCore + CryptoRng>(
+        payload: Vec<u8>,
+        dummy: &str,
+        key: Option<&str>,
+        compression_level: Option<i32>,
+        rng: &mut R,
+    ) -> Result<String, Error> {
+        hide(payload, dummy, key, compression_level, true, rng)
+    }
+
+    /// Hides a payload after the first space of a string, optionally binding it to the visible text of the string
+    fn hide<R: RngCore + CryptoRng>(
+        payload: Vec<u8>,
+        dummy: &str,
+        key: Option<&str>,
+        compression_level: Option<i32>,
+        bound: bool,
+        rng: &mut R,
+    ) -> Result<String, Error> {
+        let split = dummy.find(' ').ok_or(Error::NoSpaces)? + 1;
+        let cover = if bound { Some(dummy) } else { None };
+        let concealed = conceal_inner(&payload, key, compression_level, cover, rng)?;
+
+        let mut camouflaged = String::with_capacity(concealed.len() + dummy.len());
+        camouflaged.push_str(&dummy[..split]);
+        camouflaged.push_str(&concealed);
+        camouflaged.push_str(&dummy[split..]);
+        Ok(camouflaged)
+    }
+
+    /// Retrieves a compressed and optionally encrypted payload from a string,
+    /// checking the visible text of the string if the payload is bound to it
+    pub fn decamouflage(camouflaged: &str, key: Option<&str>) -> Result<Vec<u8>, Error> {
+        reveal_inner(camouflaged, key, Some(camouflaged))
+    }
+
+    /// Compresses and optionally encrypts a payload into a string made only of zero-width characters
+    ///
+    /// The output is what [`camouflage`] hides inside its dummy string, and can be placed in any carrier,
+    /// even split into several parts, as long as the zero-width characters are kept in order.
+    #[cfg(feature = "std")]
+    pub fn conceal(
+        payload: &[u8],
+        key: Option<&str>,
+        compression_level: Option<i32>,
+    ) -> Result<String, Error> {
+        use chacha20::ChaCha20Rng;
+        use rand_core::SeedableRng;
+
+        conceal_with_rng(
+            payload,
+            key,
+            compression_level,
+            &mut ChaCha20Rng::from_entropy(),
+        )
+    }
+
+    /// Compresses and optionally encrypts a payload into a string made only of zero-width characters,
+    /// generating the encryption nonce using the provided random number generator
+    pub fn conceal_with_rng<R: RngCore + CryptoRng>(
+        payload: &[u8],
+        key: Option<&str>,
+        compression_level: Option<i32>,
+        rng: &mut R,
+    ) -> Result<String, Error> {
+        conceal_inner(payload, key, compression_level, None, rng)
+    }
+
+    /// Compresses and optionally encrypts a payload into a string made only of zero-width characters,
+    /// binding it to a visible text if one is provided
+    ///
+    /// The hash of the visible text is used as associated data for encryption,
+    /// and a digest of it is appended so that a mismatch can be told apart from a wrong key.
+    fn conceal_inner<R: RngCore + CryptoRng>(
+        payload: &[u8],
+        key: Option<&str>,
+        compression_level: Option<i32>,
+        cover: Option<&str>,
+        rng: &mut R,
+    ) -> Result<String, Error> {
         use chacha20poly1305::aead::Aead;
         use generic_array::GenericArray;
-        use rand_core::{RngCore, SeedableRng};
-
-        let mut compressed_payload = Vec::with_capacity(payload.len());
-        brotli::BrotliCompress(
-            &mut payload.as_slice(),
-            &mut compressed_payload,
-            &BrotliEncoderParams {
-                quality: compression_level.unwrap_or(10),
-                size_hint: payload.len(),
-                ..Default::default()
-            },
-        )?;
+
+        let mut compressed_payload =
+            crate::heap::compress(payload, compression_level.unwrap_or(10))?;
+        let cover_hash = cover.map(cover_hash);
+        let associated_data = cover_hash.as_ref().map_or(&[][..], |h| &h[..]);
 
         if let Some(k) = key {
             let mut nonce = [0; 32];
-            ChaCha20Rng::from_entropy().fill_bytes(&mut nonce);
+            rng.fill_bytes(&mut nonce);
 
             let cipher = get_cipher(k, &nonce);
             cipher.encrypt_in_place(
                 GenericArray::from_slice(&nonce[..12]),
-                b"",
+                associated_data,
                 &mut compressed_payload,
             )?;
             compressed_payload.extend_from_slice(&nonce);
         }
+        if let Some(hash) = &cover_hash {
+            compressed_payload.extend_from_slice(&hash[..COVER_DIGEST_LEN]);
+        }
 
         let (compression, rp0, rp1) = crate::Compression::optimal(&compressed_payload);
 
-        let mut encoded_payload =
-            crate::encode_compress(compressed_payload.iter().copied(), compression);
-
-        let mut camouflaged = String::with_capacity((compressed_payload.len() * 8) + dummy.len());
-        for c in dummy.chars() {
-            camouflaged.push(c);
-            if c == ' ' {
-                for c in &mut encoded_payload {
-                    camouflaged.push(c);
-                }
-                camouflaged.push(crate::CHARS[(rp0 & 0b0011) as usize]);
-                camouflaged.push(crate::CHARS[((rp0 & 0b1100) >> 2) as usize]);
-                camouflaged.push(crate::CHARS[(rp1 & 0b0011) as usize]);
-                camouflaged.push(crate::CHARS[((rp1 & 0b1100) >> 2) as usize]);
-            }
-        }
-        if encoded_payload.next().is_some() {
-            return Err(Error::NoSpaces);
+        let mut concealed = String::with_capacity((compressed_payload.len() + 1) * 12);
+        concealed.extend(crate::encode_compress(
+            compressed_payload.iter().copied(),
+            compression,
+        ));
+        concealed.push(crate::CHARS[(rp0 & 0b0011) as usize]);
+        concealed.push(crate::CHARS[((rp0 & 0b1100) >> 2) as usize]);
+        concealed.push(crate::CHARS[(rp1 & 0b0011) as usize]);
+        concealed.push(crate::CHARS[((rp1 & 0b1100) >> 2) as usize]);
+        if cover.is_some() {
+            concealed.push(BOUND);
         }
+        Ok(concealed)
+    }
 
-        Ok(camouflaged)
+    /// Retrieves a compressed and optionally encrypted payload from the zero-width characters of a string,
+    /// ignoring any other character
+    ///
+    /// Payloads bound to their visible text can only be retrieved using [`decamouflage`].
+    pub fn reveal(concealed: &str, key: Option<&str>) -> Result<Vec<u8>, Error> {
+        reveal_inner(concealed, key, None)
     }
 
-    /// Retrieves a compressed and optionally encrypted payload from a string
-    pub fn decamouflage(camouflaged: &str, key: Option<&str>) -> Result<Vec<u8>, Error> {
-        use brotli::BrotliDecompress;
+    /// Retrieves a compressed and optionally encrypted payload from the zero-width characters of a string,
+    /// checking the provided visible text if the payload is bound to one
+    fn reveal_inner(
+        concealed: &str,
+        key: Option<&str>,
+        cover: Option<&str>,
+    ) -> Result<Vec<u8>, Error> {
         use chacha20poly1305::aead::Aead;
         use generic_array::GenericArray;
 
         let mut encoded_payload: Vec<char> =
-            camouflaged.chars().filter(|c| crate::is_zw(*c)).collect();
+            concealed.chars().filter(|c| crate::is_zw(*c)).collect();
+        let bound = encoded_payload.last() == Some(&BOUND);
+        if bound {
+            encoded_payload.pop();
+        }
 
         let c3 = encoded_payload.pop().ok_or(Error::InvalidPayload)?;
         let c2 = encoded_payload.pop().ok_or(Error::InvalidPayload)?;
@@ -520,34 +870,65 @@ mod camo {
             crate::decode_decompress(encoded_payload.into_iter(), compression)
                 .collect::<Result<Vec<u8>, _>>()?;
 
+        let cover_hash = match cover {
+            Some(c) if bound => Some(cover_hash(c)),
+            None if bound => return Err(Error::CoverMismatch),
+            _ => None,
+        };
+        if let Some(hash) = &cover_hash {
+            let digest_boundary = compressed_payload
+                .len()
+                .checked_sub(COVER_DIGEST_LEN)
+                .ok_or(Error::InvalidPayload)?;
+            if compressed_payload[digest_boundary..] != hash[..COVER_DIGEST_LEN] {
+                return Err(Error::CoverMismatch);
+            }
+            compressed_payload.truncate(digest_boundary);
+        }
+        let associated_data = cover_hash.as_ref().map_or(&[][..], |h| &h[..]);
+
         if let Some(k) = key {
             let mut nonce = [0; 32];
-            let nonce_boundary = compressed_payload.len() - 32;
+            let nonce_boundary = compressed_payload
+                .len()
+                .checked_sub(32)
+                .ok_or(Error::InvalidPayload)?;
             nonce.copy_from_slice(&compressed_payload[nonce_boundary..]);
             compressed_payload.truncate(nonce_boundary);
 
             let cipher = get_cipher(k, &nonce);
             cipher.decrypt_in_place(
                 GenericArray::from_slice(&nonce[..12]),
-                b"",
+                associated_data,
                 &mut compressed_payload,
             )?;
         }
 
-        let mut payload = Vec::with_capacity(compressed_payload.len() * 4);
-        BrotliDecompress(&mut compressed_payload.as_slice(), &mut payload)?;
+        Ok(crate::heap::decompress(&compressed_payload)?)
+    }
 
-        Ok(payload)
+    /// Hashes the visible text of a string, ignoring zero-width characters and surrounding whitespace
+    ///
+    /// Whitespace is trimmed on purpose, see [`camouflage_bound`].
+    fn cover_hash(cover: &str) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let visible: String = cover.chars().filter(|c| !crate::is_zw(*c)).collect();
+        Sha256::digest(visible.trim().as_bytes()).into()
     }
 
     /// Represents an error that might occur while hiding or retrieving a payload
     #[derive(Debug)]
+    #[non_exhaustive]
     pub enum Error {
         Zwc(crate::Error),
         Cipher(chacha20poly1305::aead::Error),
-        Brotli(std::io::Error),
+        Brotli(BrotliError),
+        #[cfg(feature = "std")]
+        Io(std::io::Error),
         NoSpaces,
         InvalidPayload,
+        CoverMismatch,
     }
     impl fmt::Display for Error {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -555,11 +936,20 @@ mod camo {
                 Self::Zwc(e) => write!(f, "zero-width character decoding error: {}", e),
                 Self::Cipher(e) => write!(f, "cipher error: {:?}", e),
                 Self::Brotli(e) => write!(f, "brotli error: {}", e),
+                #[cfg(feature = "std")]
+                Self::Io(e) => write!(f, "I/O error: {}", e),
                 Self::NoSpaces => write!(f, "no spaces in dummy string"),
                 Self::InvalidPayload => write!(f, "the payload is invalid"),
+                Self::CoverMismatch => {
+                    write!(
+                        f,
+                        "the visible text isn't the one the payload was hidden in"
+                    )
+                }
             }
         }
     }
+    #[cfg(feature = "std")]
     impl std::error::Error for Error {}
     impl From<crate::Error> for Error {
         fn from(e: crate::Error) -> Self {
@@ -571,14 +961,14 @@ mod camo {
             Self::Cipher(e)
         }
     }
-    impl From<std::io::Error> for Error {
-        fn from(e: std::io::Error) -> Self {
+    impl From<BrotliError> for Error {
+        fn from(e: BrotliError) -> Self {
             Self::Brotli(e)
         }
     }
 
     /// Generates a cipher instance from a key
-    fn get_cipher(key: &str, nonce: &[u8; 32]) -> chacha20poly1305::ChaCha20Poly1305 {
+    pub(crate) fn get_cipher(key: &str, nonce: &[u8; 32]) -> chacha20poly1305::ChaCha20Poly1305 {
         use chacha20poly1305::{aead::NewAead, ChaCha20Poly1305};
         use generic_array::GenericArray;
         use poly1305::{universal_hash::UniversalHash, Poly1305};
@@ -600,8 +990,89 @@ mod camo {
 
 #[cfg(test)]
 mod tests {
+    use crate::{Compression, DecodeDecompressIter, DecodeIter, EncodeCompressIter, EncodeIter};
+    use proptest::prelude::*;
+
     static SRC: &[u8] = include_bytes!("./lib.rs");
 
+    /// Asserts that the size hints of an iterator bound the number of items left at every step
+    pub(crate) fn assert_size_hints<T: Iterator>(mut iter: T, len: usize) {
+        for remaining in (0..=len).rev() {
+            let (lower, upper) = iter.size_hint();
+            assert!(lower <= remaining, "{} > {}", lower, remaining);
+            assert!(
+                upper.is_none_or(|u| remaining <= u),
+                "{:?} < {}",
+                upper,
+                remaining
+            );
+            assert_eq!(iter.next().is_some(), remaining != 0);
+        }
+    }
+
+    /// Arbitrary zero-width and non-zero-width characters
+    fn chars() -> impl Strategy<Value = Vec<char>> {
+        prop::collection::vec(
+            prop_oneof![prop::sample::select(crate::CHARS.to_vec()), Just('a')],
+            0..64,
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn encode_size_hints(data: Vec<u8>, p0 in 0u8..16, p1 in 0u8..16) {
+            let compression = Compression::new(p0, p1).unwrap();
+
+            let len = crate::encode(data.iter().copied()).count();
+            prop_assert_eq!(EncodeIter::new(data.iter().copied()).len(), len);
+            assert_size_hints(EncodeIter::new(data.iter().copied()), len);
+
+            let len = crate::encode_compress(data.iter().copied(), compression).count();
+            assert_size_hints(EncodeCompressIter::new(data.iter().copied(), compression), len);
+        }
+
+        #[test]
+        fn decode_size_hints(chars in chars(), p0 in 0u8..16, p1 in 0u8..16) {
+            let compression = Compression::new(p0, p1).unwrap();
+
+            let len = crate::decode(chars.iter().copied()).count();
+            prop_assert_eq!(DecodeIter::new(chars.iter().copied()).len(), len);
+            assert_size_hints(DecodeIter::new(chars.iter().copied()), len);
+
+            let len = crate::decode_decompress(chars.iter().copied(), compression).count();
+            assert_size_hints(DecodeDecompressIter::new(chars.iter().copied(), compression), len);
+        }
+
+        #[test]
+        fn double_ended(data: Vec<u8>, chars in chars(), p0 in 0u8..16, p1 in 0u8..16) {
+            let compression = Compression::new(p0, p1).unwrap();
+
+            let mut forward: Vec<char> = crate::encode(data.iter().copied()).collect();
+            forward.reverse();
+            prop_assert_eq!(EncodeIter::new(data.iter().copied()).rev().collect::<Vec<_>>(), forward);
+
+            let mut forward: Vec<char> = crate::encode_compress(data.iter().copied(), compression).collect();
+            forward.reverse();
+            let backward: Vec<char> = EncodeCompressIter::new(data.iter().copied(), compression).rev().collect();
+            prop_assert_eq!(backward, forward);
+
+            let mut forward: Vec<_> = crate::decode(chars.iter().copied()).collect();
+            forward.reverse();
+            prop_assert_eq!(DecodeIter::new(chars.iter().copied()).rev().collect::<Vec<_>>(), forward);
+
+            // Consuming from both ends meets in the middle
+            let mut iter = EncodeIter::new(data.iter().copied());
+            let mut front = Vec::new();
+            let mut back = Vec::new();
+            while let Some(c) = iter.next() {
+                front.push(c);
+                back.extend(iter.next_back());
+            }
+            front.extend(back.into_iter().rev());
+            prop_assert!(front.into_iter().eq(crate::encode(data.iter().copied())));
+        }
+    }
+
     #[test]
     fn round_trip() {
         let encoded = crate::encode(SRC.iter().copied());
@@ -623,7 +1094,31 @@ mod tests {
         }
     }
 
-    #[cfg(feature = "camouflage")]
+    #[test]
+    fn nameable_iterators() {
+        struct Parser<'a> {
+            decoder: DecodeIter<core::str::Chars<'a>>,
+        }
+
+        let encoded: String = EncodeIter::new(SRC.iter().copied()).collect();
+        let mut parser = Parser {
+            decoder: crate::decode(encoded.chars()),
+        };
+        parser.decoder.next();
+
+        let snapshot = parser.decoder.clone();
+        assert!(snapshot.eq(parser.decoder));
+        assert!(format!("{:?}", crate::encode(SRC.iter().copied())).starts_with("EncodeIter"));
+    }
+
+    #[test]
+    fn optimal_compression() {
+        let data = [0x11, 0x11, 0x11, 0x22, 0x22, 0x22, 0x22, 0x22];
+        let (_, rp0, rp1) = crate::Compression::optimal(&data);
+        assert_eq!((rp0, rp1), (2, 1));
+    }
+
+    #[cfg(all(feature = "camo-core", feature = "std"))]
     #[test]
     fn camouflage_round_trip() {
         let dummy = "Hello, World!";
@@ -633,4 +1128,124 @@ mod tests {
 
         assert_eq!(SRC, decamouflaged.as_slice());
     }
+
+    #[cfg(feature = "camo-core")]
+    #[test]
+    fn camouflage_with_rng() {
+        use rand_core::SeedableRng;
+
+        let mut rng = chacha20::ChaCha20Rng::seed_from_u64(0);
+        let camouflaged = crate::camouflage_with_rng(
+            SRC.to_vec(),
+            "Hello, World!",
+            Some("secret"),
+            None,
+            &mut rng,
+        )
+        .unwrap();
+        let decamouflaged = crate::decamouflage(&camouflaged, Some("secret")).unwrap();
+        assert_eq!(SRC, decamouflaged.as_slice());
+
+        let truncated: String = camouflaged.chars().skip(10).collect();
+        assert!(crate::decamouflage(&truncated, None).is_err());
+
+        let camouflaged =
+            crate::camouflage_with_rng(SRC.to_vec(), "a b c d", None, None, &mut rng).unwrap();
+        assert!(camouflaged.starts_with("a ") && camouflaged.ends_with("b c d"));
+        assert!(camouflaged[2..camouflaged.len() - 5]
+            .chars()
+            .all(crate::is_zw));
+        assert_eq!(
+            SRC,
+            crate::decamouflage(&camouflaged, None).unwrap().as_slice()
+        );
+    }
+
+    #[cfg(feature = "camo-core")]
+    #[test]
+    fn camouflage_bound() {
+        use rand_core::SeedableRng;
+
+        let mut rng = chacha20::ChaCha20Rng::seed_from_u64(0);
+        for key in [None, Some("secret")].iter() {
+            let camouflaged = crate::camouflage_bound_with_rng(
+                SRC.to_vec(),
+                "Meet me at noon",
+                *key,
+                None,
+                &mut rng,
+            )
+            .unwrap();
+            // leading and trailing whitespace isn't part of the bound text
+            assert_eq!(
+                SRC,
+                crate::decamouflage(&format!("{}\n", camouflaged), *key)
+                    .unwrap()
+                    .as_slice()
+            );
+            assert_eq!(
+                SRC,
+                crate::decamouflage(&format!(" \t{}  \r\n", camouflaged), *key)
+                    .unwrap()
+                    .as_slice()
+            );
+            let spaced = camouflaged.replacen(' ', "  ", 1);
+            assert!(matches!(
+                crate::decamouflage(&spaced, *key),
+                Err(crate::camo::Error::CoverMismatch)
+            ));
+
+            let edited = camouflaged.replace("noon", "midnight");
+            assert!(matches!(
+                crate::decamouflage(&edited, *key),
+                Err(crate::camo::Error::CoverMismatch)
+            ));
+            let hidden: String = camouflaged.chars().filter(|c| crate::is_zw(*c)).collect();
+            let transplanted = format!("Meet me {}at noon!", hidden);
+            assert!(matches!(
+                crate::decamouflage(&transplanted, *key),
+                Err(crate::camo::Error::CoverMismatch)
+            ));
+            assert!(matches!(
+                crate::reveal(&camouflaged, *key),
+                Err(crate::camo::Error::CoverMismatch)
+            ));
+        }
+
+        let camouflaged =
+            crate::camouflage_bound_with_rng(SRC.to_vec(), "a b", Some("secret"), None, &mut rng)
+                .unwrap();
+        assert!(matches!(
+            crate::decamouflage(&camouflaged, Some("other")),
+            Err(crate::camo::Error::Cipher(_))
+        ));
+
+        // removing the marker of a keyed payload doesn't unbind it
+        let marker = camouflaged.rfind(crate::CHARS[4]).unwrap();
+        let unbound = format!("{}{}", &camouflaged[..marker], &camouflaged[marker + 3..]);
+        assert!(crate::decamouflage(&unbound, Some("secret")).is_err());
+    }
+
+    #[cfg(feature = "camo-core")]
+    #[test]
+    fn conceal_reveal() {
+        use rand_core::SeedableRng;
+
+        let mut rng = chacha20::ChaCha20Rng::seed_from_u64(0);
+        let concealed = crate::conceal_with_rng(SRC, Some("secret"), None, &mut rng).unwrap();
+        assert!(concealed.chars().all(crate::is_zw));
+
+        let chars: Vec<char> = concealed.chars().collect();
+        let mixed: String = chars
+            .chunks(7)
+            .map(|c| format!("{}text", c.iter().collect::<String>()))
+            .collect();
+        assert_eq!(
+            SRC,
+            crate::reveal(&mixed, Some("secret")).unwrap().as_slice()
+        );
+
+        let short = crate::conceal_with_rng(b"", None, None, &mut rng).unwrap();
+        assert!(crate::reveal(&short, Some("secret")).is_err());
+    }
 }
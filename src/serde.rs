@@ -0,0 +1,142 @@
+//! Serde helpers serializing bytes as zero-width strings
+//!
+//! The module can be used directly with `#[serde(with = "zwc::serde")]` on `Vec<u8>` or `[u8; N]` fields,
+//! and [`Hidden`] carries a payload after a visible string value.
+
+use ::serde::de::{self, Deserialize, Deserializer, Visitor};
+use ::serde::ser::{Serialize, Serializer};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::marker::PhantomData;
+
+/// Serializes bytes as a zero-width string
+pub fn serialize<T: AsRef<[u8]>, S: Serializer>(
+    bytes: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&crate::encode_to_string(bytes.as_ref()))
+}
+
+/// Deserializes bytes from a zero-width string
+pub fn deserialize<'de, T: TryFrom<Vec<u8>>, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    deserializer.deserialize_str(BytesVisitor(PhantomData))
+}
+
+/// Visitor decoding zero-width strings into bytes
+struct BytesVisitor<T>(PhantomData<T>);
+impl<'de, T: TryFrom<Vec<u8>>> Visitor<'de> for BytesVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a zero-width string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        let bytes = crate::decode_to_vec(v).map_err(E::custom)?;
+        let len = bytes.len();
+        T::try_from(bytes).map_err(|_| E::invalid_length(len, &self))
+    }
+}
+
+/// Visible string value followed by a hidden payload encoded as zero-width characters
+///
+/// It is serialized as a single string, so it can replace a plain string field transparently.
+/// When deserializing, the trailing zero-width characters of the string are decoded as the payload.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Hidden {
+    /// Visible string value
+    pub visible: String,
+    /// Hidden payload
+    pub payload: Vec<u8>,
+}
+impl Hidden {
+    /// Creates a value hiding a payload after a visible string
+    pub fn new<S: Into<String>, P: Into<Vec<u8>>>(visible: S, payload: P) -> Self {
+        Self {
+            visible: visible.into(),
+            payload: payload.into(),
+        }
+    }
+}
+impl fmt::Display for Hidden {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.visible)?;
+        f.write_str(&crate::encode_to_string(&self.payload))
+    }
+}
+impl Serialize for Hidden {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+impl<'de> Deserialize<'de> for Hidden {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(HiddenVisitor)
+    }
+}
+
+/// Visitor splitting strings into their visible value and hidden payload
+struct HiddenVisitor;
+impl<'de> Visitor<'de> for HiddenVisitor {
+    type Value = Hidden;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a string optionally followed by zero-width characters")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        let split = v
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| crate::is_zw(*c))
+            .last()
+            .map_or(v.len(), |(i, _)| i);
+        let payload = crate::decode_to_vec(&v[split..]).map_err(E::custom)?;
+        Ok(Hidden::new(&v[..split], payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Metadata {
+        #[serde(with = "crate::serde")]
+        id: [u8; 4],
+        #[serde(with = "crate::serde")]
+        tags: Vec<u8>,
+        title: crate::serde::Hidden,
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let metadata = Metadata {
+            id: [1, 2, 3, 4],
+            tags: b"secret".to_vec(),
+            title: crate::serde::Hidden::new("Hello, World!", &b"hidden"[..]),
+        };
+        let json = serde_json::to_string(&metadata).unwrap();
+        assert!(json.contains("\"title\":\"Hello, World!\u{200C}"));
+        assert_eq!(metadata, serde_json::from_str(&json).unwrap());
+
+        let title: crate::serde::Hidden = serde_json::from_str("\"plain\"").unwrap();
+        assert_eq!(title, crate::serde::Hidden::new("plain", &b""[..]));
+    }
+
+    #[test]
+    fn serde_errors() {
+        let json = format!(
+            "{{\"id\":\"{}\",\"tags\":\"\",\"title\":\"\"}}",
+            crate::encode_to_string(b"abc")
+        );
+        assert!(serde_json::from_str::<Metadata>(&json).is_err());
+
+        let json = "{\"id\":\"abcd\",\"tags\":\"\",\"title\":\"\"}";
+        assert!(serde_json::from_str::<Metadata>(json).is_err());
+    }
+}
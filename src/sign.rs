@@ -0,0 +1,198 @@
+//! Payloads signed using Ed25519, proving who hid them
+//!
+//! A signed container is made of the payload followed by the public key of the signer and the signature.
+//! It is hidden like any other payload, so it can be compressed, encrypted and placed in any carrier.
+
+use crate::camo::{self, reveal};
+use alloc::vec::Vec;
+use core::fmt;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+/// Length of a public key
+pub const PUBLIC_KEY_LEN: usize = 32;
+/// Length of a signature
+const SIGNATURE_LEN: usize = 64;
+
+/// Payload retrieved from a signed container along with the public key of its signer
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Signed {
+    pub payload: Vec<u8>,
+    pub public_key: [u8; PUBLIC_KEY_LEN],
+}
+
+/// Represents an error that might occur while signing a payload or checking its signature
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    Camo(camo::Error),
+    Signature(ed25519_dalek::SignatureError),
+    UnexpectedSigner,
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Camo(e) => write!(f, "{}", e),
+            Self::Signature(e) => write!(f, "signature error: {}", e),
+            Self::UnexpectedSigner => write!(f, "signed by an unexpected key"),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+impl From<camo::Error> for Error {
+    fn from(e: camo::Error) -> Self {
+        Self::Camo(e)
+    }
+}
+impl From<ed25519_dalek::SignatureError> for Error {
+    fn from(e: ed25519_dalek::SignatureError) -> Self {
+        Self::Signature(e)
+    }
+}
+
+/// Derives the public key of a secret key
+pub fn public_key(secret_key: &[u8; 32]) -> [u8; PUBLIC_KEY_LEN] {
+    SigningKey::from_bytes(secret_key)
+        .verifying_key()
+        .to_bytes()
+}
+
+/// Signs a payload, producing a signed container
+pub fn sign(payload: &[u8], secret_key: &[u8; 32]) -> Vec<u8> {
+    let signing_key = SigningKey::from_bytes(secret_key);
+    let signature = signing_key.sign(payload);
+
+    let mut container = Vec::with_capacity(payload.len() + PUBLIC_KEY_LEN + SIGNATURE_LEN);
+    container.extend_from_slice(payload);
+    container.extend_from_slice(signing_key.verifying_key().as_bytes());
+    container.extend_from_slice(&signature.to_bytes());
+    container
+}
+
+/// Checks the signature of a signed container, returning its payload and the public key of its signer
+///
+/// The public key is the one stored in the container, so a valid signature only proves that the payload wasn't
+/// modified since it was signed by that key. Use [`open_from`] to check who the signer is.
+pub fn open(container: &[u8]) -> Result<Signed, Error> {
+    let boundary = container
+        .len()
+        .checked_sub(PUBLIC_KEY_LEN + SIGNATURE_LEN)
+        .ok_or(camo::Error::InvalidPayload)?;
+    let (payload, rest) = container.split_at(boundary);
+    let (public_key, signature) = rest.split_at(PUBLIC_KEY_LEN);
+
+    let mut key_bytes = [0; PUBLIC_KEY_LEN];
+    key_bytes.copy_from_slice(public_key);
+    let mut signature_bytes = [0; SIGNATURE_LEN];
+    signature_bytes.copy_from_slice(signature);
+
+    VerifyingKey::from_bytes(&key_bytes)?
+        .verify_strict(payload, &Signature::from_bytes(&signature_bytes))?;
+    Ok(Signed {
+        payload: payload.to_vec(),
+        public_key: key_bytes,
+    })
+}
+
+/// Checks that a signed container was signed by the holder of a public key, returning its payload
+pub fn open_from(container: &[u8], public_key: &[u8; PUBLIC_KEY_LEN]) -> Result<Vec<u8>, Error> {
+    let signed = open(container)?;
+    if signed.public_key != *public_key {
+        return Err(Error::UnexpectedSigner);
+    }
+    Ok(signed.payload)
+}
+
+/// Hides a signed, compressed and optionally encrypted payload inside a string
+#[cfg(feature = "std")]
+pub fn camouflage_signed(
+    payload: &[u8],
+    dummy: &str,
+    key: Option<&str>,
+    compression_level: Option<i32>,
+    secret_key: &[u8; 32],
+) -> Result<alloc::string::String, Error> {
+    Ok(crate::camouflage(
+        sign(payload, secret_key),
+        dummy,
+        key,
+        compression_level,
+    )?)
+}
+
+/// Retrieves a payload hidden using [`camouflage_signed`] from a string and checks its signature
+///
+/// Anyone can produce a valid signature using their own key, so the returned public key must be compared to the
+/// expected one, or [`verify_from`] used instead.
+pub fn verify(camouflaged: &str, key: Option<&str>) -> Result<Signed, Error> {
+    open(&reveal(camouflaged, key)?)
+}
+
+/// Retrieves a payload hidden using [`camouflage_signed`] from a string,
+/// checking that it was signed by the holder of a public key
+pub fn verify_from(
+    camouflaged: &str,
+    key: Option<&str>,
+    public_key: &[u8; PUBLIC_KEY_LEN],
+) -> Result<Vec<u8>, Error> {
+    open_from(&reveal(camouflaged, key)?, public_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open, open_from, public_key, sign};
+
+    const SRC: &[u8] = include_bytes!("../samples/lorem.txt");
+    const SECRET_KEY: [u8; 32] = [7; 32];
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sign_verify() {
+        use super::{camouflage_signed, verify, verify_from};
+
+        let camouflaged =
+            camouflage_signed(SRC, "Hello, World", Some("secret"), None, &SECRET_KEY).unwrap();
+        let signed = verify(&camouflaged, Some("secret")).unwrap();
+        assert_eq!(signed.payload, SRC);
+        assert_eq!(signed.public_key, public_key(&SECRET_KEY));
+        assert_ne!(signed.public_key, public_key(&[8; 32]));
+
+        let payload = verify_from(&camouflaged, Some("secret"), &public_key(&SECRET_KEY)).unwrap();
+        assert_eq!(payload, SRC);
+        assert!(matches!(
+            verify_from(&camouflaged, Some("secret"), &public_key(&[8; 32])),
+            Err(super::Error::UnexpectedSigner)
+        ));
+
+        // the signed container is a regular payload
+        let container = crate::decamouflage(&camouflaged, Some("secret")).unwrap();
+        assert_eq!(container, sign(SRC, &SECRET_KEY));
+    }
+
+    #[test]
+    fn sign_errors() {
+        let mut container = sign(SRC, &SECRET_KEY);
+        container[0] ^= 1;
+        assert!(matches!(open(&container), Err(super::Error::Signature(_))));
+
+        // replacing the public key doesn't make the signature valid for the new signer
+        let mut container = sign(SRC, &SECRET_KEY);
+        let start = SRC.len();
+        container[start..start + 32].copy_from_slice(&public_key(&[8; 32]));
+        assert!(open(&container).is_err());
+
+        assert!(matches!(
+            open(b"too short"),
+            Err(super::Error::Camo(crate::camo::Error::InvalidPayload))
+        ));
+        assert!(open(SRC).is_err());
+
+        // a container signed by someone else is only rejected when the signer is checked
+        let container = sign(SRC, &[8; 32]);
+        assert!(open(&container).is_ok());
+        assert!(matches!(
+            open_from(&container, &public_key(&SECRET_KEY)),
+            Err(super::Error::UnexpectedSigner)
+        ));
+    }
+}
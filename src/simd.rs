@@ -0,0 +1,815 @@
+//! Bulk UTF-8 encoding and decoding
+//!
+//! Raw encoding and decoding use SSSE3 or AVX2 on x86 (detected at runtime when `std` is enabled)
+//! and NEON on AArch64, with a table-driven portable fallback.
+//! SSSE3 is the x86 baseline, processors only supporting SSE2 use the fallback.
+//! Compressed encoding and decoding are table-driven.
+
+use crate::utf8::{utf8_index, UTF8_BLOCKS, UTF8_CHARS};
+use crate::{Compression, Error, CHARS};
+
+#[cfg(any(
+    all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        any(feature = "std", target_feature = "ssse3")
+    ),
+    all(target_arch = "aarch64", target_feature = "neon")
+))]
+mod tables {
+    /// Third UTF-8 byte of each zero-width character without compression
+    pub(super) const THIRD_BYTES: [u8; 16] =
+        [0x8C, 0x8D, 0xA0, 0xA2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    /// Shuffle masks repeating each of the first four bytes four times
+    pub(super) const REPEAT: [u8; 16] = [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3];
+    /// Lanes using the lower nibble of their byte
+    pub(super) const LOWER_NIBBLES: [u8; 16] = lanes(4, 2);
+    /// Lanes using the lower half of their nibble
+    pub(super) const LOWER_HALVES: [u8; 16] = lanes(2, 1);
+
+    /// Builds a mask with the lanes whose index modulo `modulo` is below `below` set
+    const fn lanes(modulo: usize, below: usize) -> [u8; 16] {
+        let mut mask = [0; 16];
+        let mut i = 0;
+        while i < 16 {
+            if i % modulo < below {
+                mask[i] = 0xFF;
+            }
+            i += 1;
+        }
+        mask
+    }
+
+    /// Builds the shuffle masks scattering one byte per character into 48 bytes of UTF-8,
+    /// with `kind` being the position of the byte inside of each character
+    const fn scatter(kind: usize) -> [[u8; 16]; 3] {
+        let mut masks = [[0x80; 16]; 3];
+        let mut p = 0;
+        while p < 48 {
+            if p % 3 == kind {
+                masks[p / 16][p % 16] = (p / 3) as u8;
+            }
+            p += 1;
+        }
+        masks
+    }
+
+    /// Builds the shuffle masks gathering one byte per character from 48 bytes of UTF-8,
+    /// with `kind` being the position of the byte inside of each character
+    const fn gather(kind: usize) -> [[u8; 16]; 3] {
+        let mut masks = [[0x80; 16]; 3];
+        let mut c = 0;
+        while c < 16 {
+            let p = c * 3 + kind;
+            masks[p / 16][c] = (p % 16) as u8;
+            c += 1;
+        }
+        masks
+    }
+
+    /// Builds the leading UTF-8 bytes of 48 bytes of UTF-8
+    const fn leading_bytes() -> [[u8; 16]; 3] {
+        let mut bytes = [[0; 16]; 3];
+        let mut p = 0;
+        while p < 48 {
+            if p % 3 == 0 {
+                bytes[p / 16][p % 16] = 0xE2;
+            }
+            p += 1;
+        }
+        bytes
+    }
+
+    pub(super) const SCATTER_SECOND: [[u8; 16]; 3] = scatter(1);
+    pub(super) const SCATTER_THIRD: [[u8; 16]; 3] = scatter(2);
+    pub(super) const GATHER_FIRST: [[u8; 16]; 3] = gather(0);
+    pub(super) const GATHER_SECOND: [[u8; 16]; 3] = gather(1);
+    pub(super) const GATHER_THIRD: [[u8; 16]; 3] = gather(2);
+    pub(super) const LEADING_BYTES: [[u8; 16]; 3] = leading_bytes();
+}
+
+/// Encodes bytes as UTF-8 without compression, `out` must be exactly 12 times as long as `data`
+#[allow(unreachable_code)]
+pub(crate) fn encode(data: &[u8], out: &mut [u8]) {
+    debug_assert_eq!(data.len() * 12, out.len());
+
+    #[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support was just checked
+            return unsafe { x86::encode_avx2(data, out) };
+        }
+        if is_x86_feature_detected!("ssse3") {
+            // SAFETY: SSSE3 support was just checked
+            return unsafe { x86::encode_ssse3(data, out) };
+        }
+    }
+    #[cfg(all(
+        not(feature = "std"),
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "ssse3"
+    ))]
+    {
+        // SAFETY: SSSE3 support is enabled at compile time
+        return unsafe { x86::encode_ssse3(data, out) };
+    }
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+    {
+        // SAFETY: NEON support is enabled at compile time
+        return unsafe { neon::encode(data, out) };
+    }
+
+    encode_portable(data, out)
+}
+
+/// Decodes UTF-8 without compression, `data` must be exactly 12 times as long as `out`
+#[allow(unreachable_code)]
+pub(crate) fn decode(data: &[u8], out: &mut [u8]) -> Result<(), Error> {
+    debug_assert_eq!(data.len(), out.len() * 12);
+
+    #[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support was just checked
+            return unsafe { x86::decode_avx2(data, out) };
+        }
+        if is_x86_feature_detected!("ssse3") {
+            // SAFETY: SSSE3 support was just checked
+            return unsafe { x86::decode_ssse3(data, out) };
+        }
+    }
+    #[cfg(all(
+        not(feature = "std"),
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "ssse3"
+    ))]
+    {
+        // SAFETY: SSSE3 support is enabled at compile time
+        return unsafe { x86::decode_ssse3(data, out) };
+    }
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+    {
+        // SAFETY: NEON support is enabled at compile time
+        return unsafe { neon::decode(data, out) };
+    }
+
+    decode_portable(data, out)
+}
+
+/// Encodes bytes as UTF-8 without compression using a lookup table
+pub(crate) fn encode_portable(data: &[u8], out: &mut [u8]) {
+    for (b, chunk) in data.iter().copied().zip(out.chunks_exact_mut(12)) {
+        chunk.copy_from_slice(&UTF8_BLOCKS[b as usize]);
+    }
+}
+
+/// Decodes UTF-8 without compression one character at a time
+pub(crate) fn decode_portable(data: &[u8], out: &mut [u8]) -> Result<(), Error> {
+    for (block, dst) in data.chunks_exact(12).zip(out.iter_mut()) {
+        let mut b = 0;
+        for (i, bytes) in block.chunks_exact(3).enumerate() {
+            let v = match utf8_index([bytes[0], bytes[1], bytes[2]])? {
+                i @ 0..=3 => i as u8,
+                i => return Err(Error::InvalidCharacter(CHARS[i])),
+            };
+            b |= v << (i * 2);
+        }
+        *dst = b;
+    }
+    Ok(())
+}
+
+/// Encodes bytes as UTF-8 compressed using the provided settings and returns the number of bytes written,
+/// `out` must be large enough to hold the output
+pub(crate) fn encode_compress(data: &[u8], out: &mut [u8], compression: Compression) -> usize {
+    let nibbles = compressed_nibbles(compression);
+
+    let mut cursor = 0;
+    for b in data.iter().copied() {
+        for nibble in [b & 0b0000_1111, b >> 4].iter() {
+            let (bytes, len) = &nibbles[*nibble as usize];
+            out[cursor..cursor + len].copy_from_slice(&bytes[..*len]);
+            cursor += len;
+        }
+    }
+    cursor
+}
+
+/// Decodes UTF-8 decompressed using the provided settings and returns the number of bytes written
+pub(crate) fn decode_decompress(
+    data: &[u8],
+    out: &mut [u8],
+    compression: Compression,
+) -> Result<usize, Error> {
+    let patterns = [compression.g0l(), compression.g1l()];
+    let mut chars = data.chunks(3).map(|bytes| match bytes {
+        [b0, b1, b2] => utf8_index([*b0, *b1, *b2]),
+        _ => Err(Error::InvalidByte(bytes[0])),
+    });
+
+    let mut len = 0;
+    'blocks: loop {
+        let mut b = 0;
+        let mut count = 0;
+        for shift in [0, 4].iter() {
+            let nibble = match chars.next().transpose()? {
+                Some(i @ 4..=5) => {
+                    count += 1;
+                    patterns[i - 4]
+                }
+                Some(i0) => {
+                    let i1 = match chars.next().transpose()? {
+                        Some(i1 @ 0..=3) => i1,
+                        Some(i1) => return Err(Error::InvalidCharacter(CHARS[i1])),
+                        None => return Err(Error::IncompleteBlock(count + 1)),
+                    };
+                    count += 2;
+                    (i0 | (i1 << 2)) as u8
+                }
+                None if count == 0 => break 'blocks,
+                None => return Err(Error::IncompleteBlock(count)),
+            };
+            b |= nibble << shift;
+        }
+        match out.get_mut(len) {
+            Some(dst) => *dst = b,
+            None => return Err(Error::BufferTooSmall(data.len() / 6)),
+        }
+        len += 1;
+    }
+    Ok(len)
+}
+
+/// Builds the UTF-8 representations of every nibble compressed using the provided settings
+fn compressed_nibbles(compression: Compression) -> [([u8; 6], usize); 16] {
+    let mut nibbles = [([0; 6], 0); 16];
+    for (n, (bytes, len)) in nibbles.iter_mut().enumerate() {
+        let n = n as u8;
+        if n == compression.g0l() {
+            bytes[..3].copy_from_slice(&UTF8_CHARS[4]);
+            *len = 3;
+        } else if n == compression.g1l() {
+            bytes[..3].copy_from_slice(&UTF8_CHARS[5]);
+            *len = 3;
+        } else {
+            bytes[..3].copy_from_slice(&UTF8_CHARS[(n & 0b0011) as usize]);
+            bytes[3..].copy_from_slice(&UTF8_CHARS[((n & 0b1100) >> 2) as usize]);
+            *len = 6;
+        }
+    }
+    nibbles
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    any(feature = "std", target_feature = "ssse3")
+))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    use super::tables::*;
+    use super::*;
+
+    #[inline(always)]
+    unsafe fn load(bytes: &[u8; 16]) -> __m128i {
+        _mm_loadu_si128(bytes.as_ptr() as *const __m128i)
+    }
+
+    #[cfg(feature = "std")]
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn load2(bytes: &[u8; 16]) -> __m256i {
+        _mm256_broadcastsi128_si256(load(bytes))
+    }
+
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn encode_ssse3(data: &[u8], out: &mut [u8]) {
+        let repeat = load(&REPEAT);
+        let lower_nibbles = load(&LOWER_NIBBLES);
+        let lower_halves = load(&LOWER_HALVES);
+        let third_bytes = load(&THIRD_BYTES);
+        let scatter_second = [
+            load(&SCATTER_SECOND[0]),
+            load(&SCATTER_SECOND[1]),
+            load(&SCATTER_SECOND[2]),
+        ];
+        let scatter_third = [
+            load(&SCATTER_THIRD[0]),
+            load(&SCATTER_THIRD[1]),
+            load(&SCATTER_THIRD[2]),
+        ];
+        let leading_bytes = [
+            load(&LEADING_BYTES[0]),
+            load(&LEADING_BYTES[1]),
+            load(&LEADING_BYTES[2]),
+        ];
+
+        let chunks = data.chunks_exact(4);
+        let remainder = chunks.remainder();
+        for (chunk, dst) in chunks.zip(out.chunks_exact_mut(48)) {
+            let x = _mm_cvtsi32_si128(i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+            let x = _mm_shuffle_epi8(x, repeat);
+
+            // Isolate the 2 bits of each character in its own lane
+            let lo = _mm_and_si128(x, _mm_set1_epi8(0b1111));
+            let hi = _mm_and_si128(_mm_srli_epi16(x, 4), _mm_set1_epi8(0b1111));
+            let nibbles = _mm_or_si128(
+                _mm_and_si128(lower_nibbles, lo),
+                _mm_andnot_si128(lower_nibbles, hi),
+            );
+            let lo = _mm_and_si128(nibbles, _mm_set1_epi8(0b11));
+            let hi = _mm_and_si128(_mm_srli_epi16(nibbles, 2), _mm_set1_epi8(0b11));
+            let symbols = _mm_or_si128(
+                _mm_and_si128(lower_halves, lo),
+                _mm_andnot_si128(lower_halves, hi),
+            );
+
+            let second = _mm_add_epi8(
+                _mm_and_si128(_mm_srli_epi16(symbols, 1), _mm_set1_epi8(1)),
+                _mm_set1_epi8(0x80u8 as i8),
+            );
+            let third = _mm_shuffle_epi8(third_bytes, symbols);
+
+            for i in 0..3 {
+                let v = _mm_or_si128(
+                    leading_bytes[i],
+                    _mm_or_si128(
+                        _mm_shuffle_epi8(second, scatter_second[i]),
+                        _mm_shuffle_epi8(third, scatter_third[i]),
+                    ),
+                );
+                _mm_storeu_si128(dst[i * 16..].as_mut_ptr() as *mut __m128i, v);
+            }
+        }
+
+        let offset = data.len() - remainder.len();
+        encode_portable(remainder, &mut out[offset * 12..]);
+    }
+
+    #[cfg(feature = "std")]
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn encode_avx2(data: &[u8], out: &mut [u8]) {
+        let repeat = load2(&REPEAT);
+        let lower_nibbles = load2(&LOWER_NIBBLES);
+        let lower_halves = load2(&LOWER_HALVES);
+        let third_bytes = load2(&THIRD_BYTES);
+        let scatter_second = [
+            load2(&SCATTER_SECOND[0]),
+            load2(&SCATTER_SECOND[1]),
+            load2(&SCATTER_SECOND[2]),
+        ];
+        let scatter_third = [
+            load2(&SCATTER_THIRD[0]),
+            load2(&SCATTER_THIRD[1]),
+            load2(&SCATTER_THIRD[2]),
+        ];
+        let leading_bytes = [
+            load2(&LEADING_BYTES[0]),
+            load2(&LEADING_BYTES[1]),
+            load2(&LEADING_BYTES[2]),
+        ];
+
+        let chunks = data.chunks_exact(8);
+        let remainder = chunks.remainder();
+        for (chunk, dst) in chunks.zip(out.chunks_exact_mut(96)) {
+            // Each 128 bits lane handles 4 bytes independently
+            let x = _mm256_inserti128_si256(
+                _mm256_castsi128_si256(_mm_cvtsi32_si128(i32::from_le_bytes([
+                    chunk[0], chunk[1], chunk[2], chunk[3],
+                ]))),
+                _mm_cvtsi32_si128(i32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]])),
+                1,
+            );
+            let x = _mm256_shuffle_epi8(x, repeat);
+
+            let lo = _mm256_and_si256(x, _mm256_set1_epi8(0b1111));
+            let hi = _mm256_and_si256(_mm256_srli_epi16(x, 4), _mm256_set1_epi8(0b1111));
+            let nibbles = _mm256_blendv_epi8(hi, lo, lower_nibbles);
+            let lo = _mm256_and_si256(nibbles, _mm256_set1_epi8(0b11));
+            let hi = _mm256_and_si256(_mm256_srli_epi16(nibbles, 2), _mm256_set1_epi8(0b11));
+            let symbols = _mm256_blendv_epi8(hi, lo, lower_halves);
+
+            let second = _mm256_add_epi8(
+                _mm256_and_si256(_mm256_srli_epi16(symbols, 1), _mm256_set1_epi8(1)),
+                _mm256_set1_epi8(0x80u8 as i8),
+            );
+            let third = _mm256_shuffle_epi8(third_bytes, symbols);
+
+            for i in 0..3 {
+                let v = _mm256_or_si256(
+                    leading_bytes[i],
+                    _mm256_or_si256(
+                        _mm256_shuffle_epi8(second, scatter_second[i]),
+                        _mm256_shuffle_epi8(third, scatter_third[i]),
+                    ),
+                );
+                _mm_storeu_si128(
+                    dst[i * 16..].as_mut_ptr() as *mut __m128i,
+                    _mm256_castsi256_si128(v),
+                );
+                _mm_storeu_si128(
+                    dst[48 + i * 16..].as_mut_ptr() as *mut __m128i,
+                    _mm256_extracti128_si256(v, 1),
+                );
+            }
+        }
+
+        let offset = data.len() - remainder.len();
+        encode_ssse3(remainder, &mut out[offset * 12..]);
+    }
+
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn decode_ssse3(data: &[u8], out: &mut [u8]) -> Result<(), Error> {
+        let third_bytes = load(&THIRD_BYTES);
+        let gather_first = [
+            load(&GATHER_FIRST[0]),
+            load(&GATHER_FIRST[1]),
+            load(&GATHER_FIRST[2]),
+        ];
+        let gather_second = [
+            load(&GATHER_SECOND[0]),
+            load(&GATHER_SECOND[1]),
+            load(&GATHER_SECOND[2]),
+        ];
+        let gather_third = [
+            load(&GATHER_THIRD[0]),
+            load(&GATHER_THIRD[1]),
+            load(&GATHER_THIRD[2]),
+        ];
+
+        let chunks = data.chunks_exact(48);
+        let remainder = chunks.remainder();
+        for (chunk, dst) in chunks.zip(out.chunks_exact_mut(4)) {
+            let v = [
+                _mm_loadu_si128(chunk.as_ptr() as *const __m128i),
+                _mm_loadu_si128(chunk[16..].as_ptr() as *const __m128i),
+                _mm_loadu_si128(chunk[32..].as_ptr() as *const __m128i),
+            ];
+            macro_rules! gather {
+                ($masks:expr) => {
+                    _mm_or_si128(
+                        _mm_shuffle_epi8(v[0], $masks[0]),
+                        _mm_or_si128(
+                            _mm_shuffle_epi8(v[1], $masks[1]),
+                            _mm_shuffle_epi8(v[2], $masks[2]),
+                        ),
+                    )
+                };
+            }
+            let first = gather!(gather_first);
+            let second = gather!(gather_second);
+            let third = gather!(gather_third);
+
+            let hi = _mm_and_si128(second, _mm_set1_epi8(1));
+            let lo = _mm_and_si128(
+                _mm_or_si128(third, _mm_srli_epi16(third, 1)),
+                _mm_set1_epi8(1),
+            );
+            let symbols = _mm_or_si128(_mm_add_epi8(hi, hi), lo);
+
+            let valid = _mm_and_si128(
+                _mm_cmpeq_epi8(first, _mm_set1_epi8(0xE2u8 as i8)),
+                _mm_and_si128(
+                    _mm_cmpeq_epi8(
+                        _mm_andnot_si128(_mm_set1_epi8(1), second),
+                        _mm_set1_epi8(0x80u8 as i8),
+                    ),
+                    _mm_cmpeq_epi8(_mm_shuffle_epi8(third_bytes, symbols), third),
+                ),
+            );
+            if _mm_movemask_epi8(valid) != 0xFFFF {
+                // Let the portable implementation figure out the exact error
+                decode_portable(chunk, dst)?;
+                continue;
+            }
+
+            // Combine each group of 4 symbols into a byte
+            let pairs = _mm_maddubs_epi16(symbols, _mm_set1_epi16(1 | (4 << 8)));
+            let quads = _mm_madd_epi16(pairs, _mm_set1_epi32(1 | (16 << 16)));
+            let bytes = _mm_packus_epi16(_mm_packs_epi32(quads, quads), quads);
+            dst.copy_from_slice(&_mm_cvtsi128_si32(bytes).to_le_bytes());
+        }
+
+        let offset = data.len() - remainder.len();
+        decode_portable(remainder, &mut out[offset / 12..])
+    }
+
+    #[cfg(feature = "std")]
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn decode_avx2(data: &[u8], out: &mut [u8]) -> Result<(), Error> {
+        let third_bytes = load2(&THIRD_BYTES);
+        let gather_first = [
+            load2(&GATHER_FIRST[0]),
+            load2(&GATHER_FIRST[1]),
+            load2(&GATHER_FIRST[2]),
+        ];
+        let gather_second = [
+            load2(&GATHER_SECOND[0]),
+            load2(&GATHER_SECOND[1]),
+            load2(&GATHER_SECOND[2]),
+        ];
+        let gather_third = [
+            load2(&GATHER_THIRD[0]),
+            load2(&GATHER_THIRD[1]),
+            load2(&GATHER_THIRD[2]),
+        ];
+
+        let chunks = data.chunks_exact(96);
+        let remainder = chunks.remainder();
+        for (chunk, dst) in chunks.zip(out.chunks_exact_mut(8)) {
+            // Each 128 bits lane handles 48 bytes independently
+            macro_rules! load_lanes {
+                ($offset:expr) => {
+                    _mm256_inserti128_si256(
+                        _mm256_castsi128_si256(_mm_loadu_si128(
+                            chunk[$offset..].as_ptr() as *const __m128i
+                        )),
+                        _mm_loadu_si128(chunk[48 + $offset..].as_ptr() as *const __m128i),
+                        1,
+                    )
+                };
+            }
+            let v = [load_lanes!(0), load_lanes!(16), load_lanes!(32)];
+            macro_rules! gather {
+                ($masks:expr) => {
+                    _mm256_or_si256(
+                        _mm256_shuffle_epi8(v[0], $masks[0]),
+                        _mm256_or_si256(
+                            _mm256_shuffle_epi8(v[1], $masks[1]),
+                            _mm256_shuffle_epi8(v[2], $masks[2]),
+                        ),
+                    )
+                };
+            }
+            let first = gather!(gather_first);
+            let second = gather!(gather_second);
+            let third = gather!(gather_third);
+
+            let hi = _mm256_and_si256(second, _mm256_set1_epi8(1));
+            let lo = _mm256_and_si256(
+                _mm256_or_si256(third, _mm256_srli_epi16(third, 1)),
+                _mm256_set1_epi8(1),
+            );
+            let symbols = _mm256_or_si256(_mm256_add_epi8(hi, hi), lo);
+
+            let valid = _mm256_and_si256(
+                _mm256_cmpeq_epi8(first, _mm256_set1_epi8(0xE2u8 as i8)),
+                _mm256_and_si256(
+                    _mm256_cmpeq_epi8(
+                        _mm256_andnot_si256(_mm256_set1_epi8(1), second),
+                        _mm256_set1_epi8(0x80u8 as i8),
+                    ),
+                    _mm256_cmpeq_epi8(_mm256_shuffle_epi8(third_bytes, symbols), third),
+                ),
+            );
+            if _mm256_movemask_epi8(valid) != -1 {
+                decode_portable(chunk, dst)?;
+                continue;
+            }
+
+            let pairs = _mm256_maddubs_epi16(symbols, _mm256_set1_epi16(1 | (4 << 8)));
+            let quads = _mm256_madd_epi16(pairs, _mm256_set1_epi32(1 | (16 << 16)));
+            let bytes = _mm256_packus_epi16(_mm256_packs_epi32(quads, quads), quads);
+            dst[..4]
+                .copy_from_slice(&_mm_cvtsi128_si32(_mm256_castsi256_si128(bytes)).to_le_bytes());
+            dst[4..].copy_from_slice(
+                &_mm_cvtsi128_si32(_mm256_extracti128_si256(bytes, 1)).to_le_bytes(),
+            );
+        }
+
+        let offset = data.len() - remainder.len();
+        decode_ssse3(remainder, &mut out[offset / 12..])
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod neon {
+    use core::arch::aarch64::*;
+
+    use super::tables::*;
+    use super::*;
+
+    #[inline(always)]
+    unsafe fn load(bytes: &[u8; 16]) -> uint8x16_t {
+        vld1q_u8(bytes.as_ptr())
+    }
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn encode(data: &[u8], out: &mut [u8]) {
+        let repeat = load(&REPEAT);
+        let lower_nibbles = load(&LOWER_NIBBLES);
+        let lower_halves = load(&LOWER_HALVES);
+        let third_bytes = load(&THIRD_BYTES);
+        let scatter_second = [
+            load(&SCATTER_SECOND[0]),
+            load(&SCATTER_SECOND[1]),
+            load(&SCATTER_SECOND[2]),
+        ];
+        let scatter_third = [
+            load(&SCATTER_THIRD[0]),
+            load(&SCATTER_THIRD[1]),
+            load(&SCATTER_THIRD[2]),
+        ];
+        let leading_bytes = [
+            load(&LEADING_BYTES[0]),
+            load(&LEADING_BYTES[1]),
+            load(&LEADING_BYTES[2]),
+        ];
+
+        let chunks = data.chunks_exact(4);
+        let remainder = chunks.remainder();
+        for (chunk, dst) in chunks.zip(out.chunks_exact_mut(48)) {
+            let x = vreinterpretq_u8_u32(vdupq_n_u32(u32::from_le_bytes([
+                chunk[0], chunk[1], chunk[2], chunk[3],
+            ])));
+            let x = vqtbl1q_u8(x, repeat);
+
+            let lo = vandq_u8(x, vdupq_n_u8(0b1111));
+            let hi = vshrq_n_u8(x, 4);
+            let nibbles = vbslq_u8(lower_nibbles, lo, hi);
+            let lo = vandq_u8(nibbles, vdupq_n_u8(0b11));
+            let hi = vshrq_n_u8(nibbles, 2);
+            let symbols = vbslq_u8(lower_halves, lo, hi);
+
+            let second = vaddq_u8(vshrq_n_u8(symbols, 1), vdupq_n_u8(0x80));
+            let third = vqtbl1q_u8(third_bytes, symbols);
+
+            for i in 0..3 {
+                let v = vorrq_u8(
+                    leading_bytes[i],
+                    vorrq_u8(
+                        vqtbl1q_u8(second, scatter_second[i]),
+                        vqtbl1q_u8(third, scatter_third[i]),
+                    ),
+                );
+                vst1q_u8(dst[i * 16..].as_mut_ptr(), v);
+            }
+        }
+
+        let offset = data.len() - remainder.len();
+        encode_portable(remainder, &mut out[offset * 12..]);
+    }
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn decode(data: &[u8], out: &mut [u8]) -> Result<(), Error> {
+        let third_bytes = load(&THIRD_BYTES);
+        let gather_first = [
+            load(&GATHER_FIRST[0]),
+            load(&GATHER_FIRST[1]),
+            load(&GATHER_FIRST[2]),
+        ];
+        let gather_second = [
+            load(&GATHER_SECOND[0]),
+            load(&GATHER_SECOND[1]),
+            load(&GATHER_SECOND[2]),
+        ];
+        let gather_third = [
+            load(&GATHER_THIRD[0]),
+            load(&GATHER_THIRD[1]),
+            load(&GATHER_THIRD[2]),
+        ];
+        let weights = vld1q_u8([1, 4, 16, 64, 1, 4, 16, 64, 1, 4, 16, 64, 1, 4, 16, 64].as_ptr());
+
+        let chunks = data.chunks_exact(48);
+        let remainder = chunks.remainder();
+        for (chunk, dst) in chunks.zip(out.chunks_exact_mut(4)) {
+            let v = [
+                vld1q_u8(chunk.as_ptr()),
+                vld1q_u8(chunk[16..].as_ptr()),
+                vld1q_u8(chunk[32..].as_ptr()),
+            ];
+            macro_rules! gather {
+                ($masks:expr) => {
+                    vorrq_u8(
+                        vqtbl1q_u8(v[0], $masks[0]),
+                        vorrq_u8(vqtbl1q_u8(v[1], $masks[1]), vqtbl1q_u8(v[2], $masks[2])),
+                    )
+                };
+            }
+            let first = gather!(gather_first);
+            let second = gather!(gather_second);
+            let third = gather!(gather_third);
+
+            let hi = vandq_u8(second, vdupq_n_u8(1));
+            let lo = vandq_u8(vorrq_u8(third, vshrq_n_u8(third, 1)), vdupq_n_u8(1));
+            let symbols = vorrq_u8(vaddq_u8(hi, hi), lo);
+
+            let valid = vandq_u8(
+                vceqq_u8(first, vdupq_n_u8(0xE2)),
+                vandq_u8(
+                    vceqq_u8(vbicq_u8(second, vdupq_n_u8(1)), vdupq_n_u8(0x80)),
+                    vceqq_u8(vqtbl1q_u8(third_bytes, symbols), third),
+                ),
+            );
+            if vminvq_u8(valid) != 0xFF {
+                decode_portable(chunk, dst)?;
+                continue;
+            }
+
+            let quads = vpaddlq_u16(vpaddlq_u8(vmulq_u8(symbols, weights)));
+            let bytes = vmovn_u16(vcombine_u16(vmovn_u32(quads), vmovn_u32(quads)));
+            dst.copy_from_slice(&vget_lane_u32(vreinterpret_u32_u8(bytes), 0).to_le_bytes());
+        }
+
+        let offset = data.len() - remainder.len();
+        decode_portable(remainder, &mut out[offset / 12..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// Deterministic pseudo-random bytes
+    fn bytes(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bulk_matches_iterators() {
+        for len in (0..100).chain(Some(4099)) {
+            let data = bytes(len);
+
+            let mut encoded = vec![0; len * 12];
+            super::encode(&data, &mut encoded);
+            assert!(encoded
+                .iter()
+                .copied()
+                .eq(crate::encode_utf8(data.iter().copied())));
+
+            let mut decoded = vec![0; len];
+            super::decode(&encoded, &mut decoded).unwrap();
+            assert_eq!(data, decoded);
+
+            let (compression, ..) = crate::Compression::optimal(&data);
+            let mut encoded = vec![0; len * 12];
+            let encoded_len = super::encode_compress(&data, &mut encoded, compression);
+            assert!(encoded[..encoded_len]
+                .iter()
+                .copied()
+                .eq(crate::encode_compress_utf8(
+                    data.iter().copied(),
+                    compression
+                )));
+
+            let decoded_len =
+                super::decode_decompress(&encoded[..encoded_len], &mut decoded, compression)
+                    .unwrap();
+            assert_eq!(data, &decoded[..decoded_len]);
+        }
+    }
+
+    #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "std"))]
+    #[test]
+    fn x86_matches_portable() {
+        let data = bytes(4099);
+        let mut expected = vec![0; data.len() * 12];
+        super::encode_portable(&data, &mut expected);
+
+        let mut encoded = vec![0; data.len() * 12];
+        let mut decoded = vec![0; data.len()];
+        if is_x86_feature_detected!("ssse3") {
+            unsafe { super::x86::encode_ssse3(&data, &mut encoded) };
+            assert_eq!(expected, encoded);
+            unsafe { super::x86::decode_ssse3(&encoded, &mut decoded) }.unwrap();
+            assert_eq!(data, decoded);
+        }
+        if is_x86_feature_detected!("avx2") {
+            unsafe { super::x86::encode_avx2(&data, &mut encoded) };
+            assert_eq!(expected, encoded);
+            unsafe { super::x86::decode_avx2(&encoded, &mut decoded) }.unwrap();
+            assert_eq!(data, decoded);
+        }
+    }
+
+    #[test]
+    fn bulk_errors() {
+        let data = bytes(64);
+        let mut encoded = vec![0; 64 * 12];
+        super::encode(&data, &mut encoded);
+        let mut decoded = vec![0; 64];
+
+        for (i, c) in [(0, '\u{2063}'), (255, '\u{2064}'), (700, '\u{2063}')].iter() {
+            let mut corrupted = encoded.clone();
+            let offset = i - (i % 3);
+            c.encode_utf8(&mut corrupted[offset..offset + 3]);
+            assert_eq!(
+                super::decode(&corrupted, &mut decoded),
+                Err(crate::Error::InvalidCharacter(*c))
+            );
+        }
+
+        let mut corrupted = encoded.clone();
+        corrupted[100] = b'a';
+        assert!(super::decode(&corrupted, &mut decoded).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use crate::utf8::utf8_index;
+use crate::{Block, Compression, Error};
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec, vec::Vec};
+
+/// Returns the number of zero-width characters required to encode `len` bytes without compression
+#[inline]
+pub const fn encoded_len(len: usize) -> usize {
+    len * 4
+}
+
+/// Returns the number of UTF-8 bytes required to encode `len` bytes without compression
+#[inline]
+pub const fn encoded_str_len(len: usize) -> usize {
+    encoded_len(len) * 3
+}
+
+/// Returns the number of bytes represented by `len` zero-width characters without compression
+#[inline]
+pub const fn decoded_len(len: usize) -> usize {
+    len / 4
+}
+
+/// Returns the number of zero-width characters required to encode the provided data compressed using the provided settings
+pub fn encoded_compress_len(data: &[u8], compression: Compression) -> usize {
+    data.iter()
+        .copied()
+        .map(|b| Block::from(b).to_compressed_chars(compression).1)
+        .sum()
+}
+
+/// Encodes bytes into a caller-provided zero-width character buffer and returns the number of characters written
+pub fn encode_to_slice(data: &[u8], out: &mut [char]) -> Result<usize, Error> {
+    let len = encoded_len(data.len());
+    if out.len() < len {
+        return Err(Error::BufferTooSmall(len));
+    }
+
+    for (b, chunk) in data.iter().copied().zip(out.chunks_exact_mut(4)) {
+        chunk.copy_from_slice(&Block::from(b).to_chars());
+    }
+    Ok(len)
+}
+
+/// Encodes bytes into a caller-provided buffer as UTF-8 and returns the encoded string
+pub fn encode_to_str_buf<'a>(data: &[u8], out: &'a mut [u8]) -> Result<&'a str, Error> {
+    let len = encoded_str_len(data.len());
+    if out.len() < len {
+        return Err(Error::BufferTooSmall(len));
+    }
+
+    crate::simd::encode(data, &mut out[..len]);
+    // SAFETY: the output is only made of UTF-8 encoded zero-width characters
+    Ok(unsafe { core::str::from_utf8_unchecked(&out[..len]) })
+}
+
+/// Decodes zero-width characters into a caller-provided byte buffer and returns the number of bytes written
+pub fn decode_from_slice(chars: &[char], out: &mut [u8]) -> Result<usize, Error> {
+    let len = decoded_len(chars.len());
+    if out.len() < len {
+        return Err(Error::BufferTooSmall(len));
+    }
+
+    let blocks = chars.chunks_exact(4);
+    let remainder = blocks.remainder().len();
+    for (block, dst) in blocks.zip(out.iter_mut()) {
+        *dst = Block::from_chars([block[0], block[1], block[2], block[3]])?.into();
+    }
+    if remainder != 0 {
+        return Err(Error::IncompleteBlock(remainder));
+    }
+    Ok(len)
+}
+
+/// Decodes a zero-width string into a caller-provided byte buffer and returns the number of bytes written
+pub fn decode_from_str(s: &str, out: &mut [u8]) -> Result<usize, Error> {
+    let len = s.len() / 12;
+    if out.len() < len {
+        return Err(Error::BufferTooSmall(len));
+    }
+
+    let remainder = s.len() % 12;
+    crate::simd::decode(&s.as_bytes()[..len * 12], &mut out[..len])?;
+    if remainder != 0 {
+        for bytes in s.as_bytes()[s.len() - remainder..].chunks(3) {
+            match bytes {
+                [b0, b1, b2] => utf8_index([*b0, *b1, *b2])?,
+                _ => return Err(Error::InvalidByte(bytes[0])),
+            };
+        }
+        return Err(Error::IncompleteBlock(remainder / 3));
+    }
+    Ok(len)
+}
+
+/// Encodes bytes compressed using the provided settings into a caller-provided zero-width character buffer
+/// and returns the number of characters written
+pub fn encode_compress_to_slice(
+    data: &[u8],
+    out: &mut [char],
+    compression: Compression,
+) -> Result<usize, Error> {
+    let len = encoded_compress_len(data, compression);
+    if out.len() < len {
+        return Err(Error::BufferTooSmall(len));
+    }
+
+    let mut cursor = 0;
+    for b in data.iter().copied() {
+        let (chars, n) = Block::from(b).to_compressed_chars(compression);
+        out[cursor..cursor + n].copy_from_slice(&chars[..n]);
+        cursor += n;
+    }
+    Ok(len)
+}
+
+/// Encodes bytes compressed using the provided settings into a caller-provided buffer as UTF-8
+/// and returns the encoded string
+pub fn encode_compress_to_str_buf<'a>(
+    data: &[u8],
+    out: &'a mut [u8],
+    compression: Compression,
+) -> Result<&'a str, Error> {
+    let len = encoded_compress_len(data, compression) * 3;
+    if out.len() < len {
+        return Err(Error::BufferTooSmall(len));
+    }
+
+    crate::simd::encode_compress(data, out, compression);
+    // SAFETY: the output is only made of UTF-8 encoded zero-width characters
+    Ok(unsafe { core::str::from_utf8_unchecked(&out[..len]) })
+}
+
+/// Decodes zero-width characters decompressed using the provided settings into a caller-provided byte buffer
+/// and returns the number of bytes written
+pub fn decode_decompress_from_slice(
+    chars: &[char],
+    out: &mut [u8],
+    compression: Compression,
+) -> Result<usize, Error> {
+    // every byte takes at least two characters
+    decode_into(
+        crate::decode_decompress(chars.iter().copied(), compression),
+        out,
+        chars.len() / 2,
+    )
+}
+
+/// Decodes a zero-width string decompressed using the provided settings into a caller-provided byte buffer
+/// and returns the number of bytes written
+pub fn decode_decompress_from_str(
+    s: &str,
+    out: &mut [u8],
+    compression: Compression,
+) -> Result<usize, Error> {
+    crate::simd::decode_decompress(s.as_bytes(), out, compression)
+}
+
+/// Encodes bytes into an owned zero-width string
+#[cfg(feature = "alloc")]
+pub fn encode_to_string(data: &[u8]) -> String {
+    let mut buf = vec![0; encoded_str_len(data.len())];
+    encode_to_str_buf(data, &mut buf).unwrap();
+    // SAFETY: the buffer was entirely filled with UTF-8 encoded zero-width characters
+    unsafe { String::from_utf8_unchecked(buf) }
+}
+
+/// Encodes bytes compressed using the provided settings into an owned zero-width string
+#[cfg(feature = "alloc")]
+pub fn encode_compress_to_string(data: &[u8], compression: Compression) -> String {
+    let mut buf = vec![0; encoded_compress_len(data, compression) * 3];
+    encode_compress_to_str_buf(data, &mut buf, compression).unwrap();
+    // SAFETY: the buffer was entirely filled with UTF-8 encoded zero-width characters
+    unsafe { String::from_utf8_unchecked(buf) }
+}
+
+/// Decodes a zero-width string into owned bytes
+#[cfg(feature = "alloc")]
+pub fn decode_to_vec(s: &str) -> Result<Vec<u8>, Error> {
+    let mut out = vec![0; s.len() / 12];
+    let len = decode_from_str(s, &mut out)?;
+    out.truncate(len);
+    Ok(out)
+}
+
+/// Decodes a zero-width string decompressed using the provided settings into owned bytes
+#[cfg(feature = "alloc")]
+pub fn decode_decompress_to_vec(s: &str, compression: Compression) -> Result<Vec<u8>, Error> {
+    // Every byte takes at least two characters of three bytes each
+    let mut out = vec![0; s.len() / 6];
+    let len = decode_decompress_from_str(s, &mut out, compression)?;
+    out.truncate(len);
+    Ok(out)
+}
+
+/// Writes the output of a decoding iterator to a buffer, reporting `upper` as the required length on overflow
+fn decode_into<T: Iterator<Item = Result<u8, Error>>>(
+    iter: T,
+    out: &mut [u8],
+    upper: usize,
+) -> Result<usize, Error> {
+    let mut len = 0;
+    for b in iter {
+        let b = b?;
+        match out.get_mut(len) {
+            Some(dst) => *dst = b,
+            None => return Err(Error::BufferTooSmall(upper)),
+        }
+        len += 1;
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    static SRC: &[u8] = include_bytes!("./slice.rs");
+
+    #[test]
+    fn slice_round_trip() {
+        let mut chars = vec!['\0'; crate::encoded_len(SRC.len())];
+        let len = crate::encode_to_slice(SRC, &mut chars).unwrap();
+        assert_eq!(len, chars.len());
+
+        let mut decoded = vec![0; crate::decoded_len(chars.len())];
+        let len = crate::decode_from_slice(&chars, &mut decoded).unwrap();
+        assert_eq!(SRC, &decoded[..len]);
+
+        let mut buf = vec![0; crate::encoded_str_len(SRC.len())];
+        let s = crate::encode_to_str_buf(SRC, &mut buf).unwrap();
+        assert!(s.chars().eq(chars.iter().copied()));
+
+        let len = crate::decode_from_str(s, &mut decoded).unwrap();
+        assert_eq!(SRC, &decoded[..len]);
+    }
+
+    #[test]
+    fn compression_slice_round_trip() {
+        let (compression, ..) = crate::Compression::optimal(SRC);
+
+        let mut buf = vec![0; crate::encoded_compress_len(SRC, compression) * 3];
+        let s = crate::encode_compress_to_str_buf(SRC, &mut buf, compression).unwrap();
+        assert!(s
+            .chars()
+            .eq(crate::encode_compress(SRC.iter().copied(), compression)));
+
+        let mut decoded = vec![0; SRC.len()];
+        let len = crate::decode_decompress_from_str(s, &mut decoded, compression).unwrap();
+        assert_eq!(SRC, &decoded[..len]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn owned_round_trip() {
+        let s = crate::encode_to_string(SRC);
+        assert_eq!(SRC, crate::decode_to_vec(&s).unwrap().as_slice());
+
+        let (compression, ..) = crate::Compression::optimal(SRC);
+        let s = crate::encode_compress_to_string(SRC, compression);
+        assert_eq!(
+            SRC,
+            crate::decode_decompress_to_vec(&s, compression)
+                .unwrap()
+                .as_slice()
+        );
+    }
+
+    #[test]
+    fn buffer_too_small() {
+        let mut chars = ['\0'; 7];
+        assert_eq!(
+            crate::encode_to_slice(b"ab", &mut chars),
+            Err(crate::Error::BufferTooSmall(8))
+        );
+
+        let (compression, ..) = crate::Compression::optimal(SRC);
+        let chars: Vec<char> = crate::encode_compress(SRC.iter().copied(), compression).collect();
+        let mut out = [0; 8];
+        assert_eq!(
+            crate::decode_decompress_from_slice(&chars, &mut out, compression),
+            Err(crate::Error::BufferTooSmall(chars.len() / 2))
+        );
+    }
+}
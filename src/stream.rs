@@ -0,0 +1,379 @@
+use crate::camo::{get_cipher, Error};
+use crate::Compression;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+/// Maximum number of compressed payload bytes in a single frame
+const CHUNK_LEN: usize = 64 * 1024;
+/// Length of the Poly1305 authentication tag appended to encrypted frames
+const TAG_LEN: usize = 16;
+
+/// Stream header flag set when frames are encrypted
+const FLAG_ENCRYPTED: u8 = 0b0000_0001;
+/// Frame header flag set on the last frame of a stream
+const FLAG_LAST: u8 = 0b0000_0001;
+
+/// Hides a compressed and optionally encrypted payload read from a reader inside a string written to a writer
+///
+/// The payload is compressed using streaming Brotli and split into frames which are encrypted
+/// using the STREAM construction, so it never needs to be held in memory in its entirety.
+/// The output can only be read back using [`decamouflage_stream`].
+pub fn camouflage_stream<R: Read, W: Write>(
+    mut payload: R,
+    dummy: &str,
+    key: Option<&str>,
+    compression_level: Option<i32>,
+    mut out: W,
+) -> Result<W, Error> {
+    use chacha20::ChaCha20Rng;
+    use rand_core::{RngCore, SeedableRng};
+
+    let split = dummy.find(' ').ok_or(Error::NoSpaces)? + 1;
+    out.write_all(&dummy.as_bytes()[..split])
+        .map_err(Error::Io)?;
+
+    let mut frames = FrameWriter {
+        inner: out,
+        cipher: None,
+        flags: 0,
+        buffer: Vec::with_capacity(CHUNK_LEN + TAG_LEN),
+        counter: 0,
+        error: None,
+    };
+    let mut header = [0; 33];
+    let header = match key {
+        Some(k) => {
+            ChaCha20Rng::from_entropy().fill_bytes(&mut header[1..]);
+            let mut salt = [0; 32];
+            salt.copy_from_slice(&header[1..]);
+
+            header[0] = FLAG_ENCRYPTED;
+            frames.cipher = Some((get_cipher(k, &salt), salt));
+            frames.flags = header[0];
+            &header[..]
+        }
+        None => &header[..1],
+    };
+    frames.write_raw(header)?;
+
+    let mut compressor = brotli::CompressorWriter::new(
+        frames,
+        CHUNK_LEN,
+        compression_level.unwrap_or(10) as u32,
+        22,
+    );
+    if let Err(e) = io::copy(&mut payload, &mut compressor) {
+        return Err(compressor.get_mut().error.take().unwrap_or(Error::Io(e)));
+    }
+    let mut frames = compressor.into_inner();
+    if let Some(e) = frames.error.take() {
+        return Err(e);
+    }
+    frames.finish()?;
+
+    let mut out = frames.inner;
+    out.write_all(&dummy.as_bytes()[split..])
+        .map_err(Error::Io)?;
+    Ok(out)
+}
+
+/// Retrieves a compressed and optionally encrypted payload hidden using [`camouflage_stream`]
+/// from a reader and writes it to a writer
+pub fn decamouflage_stream<R: Read, W: Write>(
+    camouflaged: R,
+    key: Option<&str>,
+    out: W,
+) -> Result<W, Error> {
+    use chacha20poly1305::aead::Aead;
+    use generic_array::GenericArray;
+
+    let mut chars = ZwChars {
+        inner: BufReader::new(camouflaged),
+        error: None,
+    };
+
+    let mut flags = [0];
+    chars.read_bytes(&mut flags, None)?;
+    let cipher = if flags[0] & FLAG_ENCRYPTED != 0 {
+        let mut salt = [0; 32];
+        chars.read_bytes(&mut salt, None)?;
+        let k = key.ok_or(Error::InvalidPayload)?;
+        Some((get_cipher(k, &salt), salt))
+    } else if key.is_some() {
+        // An unencrypted stream can't have been produced using a key
+        return Err(Error::InvalidPayload);
+    } else {
+        None
+    };
+
+    let mut decompressor = brotli::DecompressorWriter::new(out, CHUNK_LEN);
+    let mut buffer = Vec::with_capacity(CHUNK_LEN + TAG_LEN);
+    let mut counter = 0;
+    loop {
+        let mut header = [0; 6];
+        chars.read_bytes(&mut header, None)?;
+        let last = header[0] & FLAG_LAST != 0;
+        let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        if len > CHUNK_LEN + TAG_LEN {
+            return Err(Error::InvalidPayload);
+        }
+        let compression = Compression::new(header[5] & 0b0000_1111, header[5] >> 4)?;
+
+        buffer.resize(len, 0);
+        chars.read_bytes(&mut buffer, Some(compression))?;
+        if let Some((cipher, salt)) = &cipher {
+            cipher.decrypt_in_place(
+                GenericArray::from_slice(&frame_nonce(salt, counter, last)?),
+                &flags[..],
+                &mut buffer,
+            )?;
+        }
+        decompressor.write_all(&buffer).map_err(Error::Brotli)?;
+
+        if last {
+            break;
+        }
+        counter += 1;
+    }
+
+    decompressor.close().map_err(Error::Brotli)?;
+    decompressor.into_inner().map_err(|_| Error::InvalidPayload)
+}
+
+/// Returns the nonce of a frame, made of the first 7 bytes of the salt, the frame counter and the last frame flag
+fn frame_nonce(salt: &[u8; 32], counter: u64, last: bool) -> Result<[u8; 12], Error> {
+    if counter > u64::from(u32::MAX) {
+        return Err(Error::InvalidPayload);
+    }
+
+    let mut nonce = [0; 12];
+    nonce[..7].copy_from_slice(&salt[..7]);
+    nonce[7..11].copy_from_slice(&(counter as u32).to_be_bytes());
+    nonce[11] = last as u8;
+    Ok(nonce)
+}
+
+/// Writer splitting compressed data into encoded frames
+struct FrameWriter<W: Write> {
+    inner: W,
+    cipher: Option<(chacha20poly1305::ChaCha20Poly1305, [u8; 32])>,
+    // The stream header flags are authenticated as the associated data of every frame
+    flags: u8,
+    buffer: Vec<u8>,
+    counter: u64,
+    // Brotli discards errors happening while finishing the stream so they're kept around
+    error: Option<Error>,
+}
+impl<W: Write> FrameWriter<W> {
+    /// Writes bytes without compression
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), Error> {
+        let mut buf = vec![0; crate::encoded_str_len(data.len())];
+        let encoded = crate::encode_to_str_buf(data, &mut buf)?;
+        self.inner.write_all(encoded.as_bytes()).map_err(Error::Io)
+    }
+
+    /// Encrypts and writes the buffered data as a frame
+    fn write_frame(&mut self, last: bool) -> Result<(), Error> {
+        use chacha20poly1305::aead::Aead;
+        use generic_array::GenericArray;
+
+        if let Some((cipher, salt)) = &self.cipher {
+            cipher.encrypt_in_place(
+                GenericArray::from_slice(&frame_nonce(salt, self.counter, last)?),
+                &[self.flags][..],
+                &mut self.buffer,
+            )?;
+        }
+
+        let (compression, rp0, rp1) = Compression::optimal(&self.buffer);
+        let len = (self.buffer.len() as u32).to_le_bytes();
+        self.write_raw(&[last as u8, len[0], len[1], len[2], len[3], rp0 | (rp1 << 4)])?;
+
+        let mut buf = vec![0; crate::encoded_compress_len(&self.buffer, compression) * 3];
+        let encoded = crate::encode_compress_to_str_buf(&self.buffer, &mut buf, compression)?;
+        self.inner
+            .write_all(encoded.as_bytes())
+            .map_err(Error::Io)?;
+
+        self.buffer.clear();
+        self.counter += 1;
+        Ok(())
+    }
+
+    /// Writes the last frame
+    fn finish(&mut self) -> Result<(), Error> {
+        self.write_frame(true)
+    }
+}
+impl<W: Write> Write for FrameWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() == CHUNK_LEN {
+            if let Err(e) = self.write_frame(false) {
+                self.error = Some(e);
+                return Err(io::Error::other("failed to write frame"));
+            }
+        }
+
+        let len = buf.len().min(CHUNK_LEN - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Iterator over the zero-width characters of a UTF-8 reader, ignoring everything else
+struct ZwChars<R: BufRead> {
+    inner: R,
+    error: Option<io::Error>,
+}
+impl<R: BufRead> ZwChars<R> {
+    /// Returns the next byte of the reader
+    fn next_byte(&mut self) -> Option<u8> {
+        loop {
+            match self.inner.fill_buf() {
+                Ok([]) => return None,
+                Ok(buf) => {
+                    let b = buf[0];
+                    self.inner.consume(1);
+                    return Some(b);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.error = Some(e);
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Fills a buffer with bytes decoded using the provided compression settings, if any
+    fn read_bytes(
+        &mut self,
+        buf: &mut [u8],
+        compression: Option<Compression>,
+    ) -> Result<(), Error> {
+        let (len, result) = match compression {
+            Some(compression) => fill(buf, crate::decode_decompress(self.by_ref(), compression)),
+            None => fill(buf, crate::decode(self.by_ref())),
+        };
+
+        if let Some(e) = self.error.take() {
+            return Err(Error::Io(e));
+        }
+        result?;
+        if len < buf.len() {
+            return Err(Error::InvalidPayload);
+        }
+        Ok(())
+    }
+}
+impl<R: BufRead> Iterator for ZwChars<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Every zero-width character starts with 0xE2, which only ever appears as a leading byte
+            if self.next_byte()? != 0xE2 {
+                continue;
+            }
+            let b1 = self.next_byte()?;
+            let b2 = self.next_byte()?;
+            if let Ok(i) = crate::utf8::utf8_index([0xE2, b1, b2]) {
+                return Some(crate::CHARS[i]);
+            }
+        }
+    }
+}
+
+/// Fills a buffer with the output of a decoding iterator, returning the number of bytes written
+fn fill<T: Iterator<Item = Result<u8, crate::Error>>>(
+    buf: &mut [u8],
+    iter: T,
+) -> (usize, Result<(), crate::Error>) {
+    let mut len = 0;
+    // The buffer goes first so no extra byte is pulled from the iterator once it is full
+    for (dst, b) in buf.iter_mut().zip(iter) {
+        match b {
+            Ok(b) => *dst = b,
+            Err(e) => return (len, Err(e)),
+        }
+        len += 1;
+    }
+    (len, Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    static SAMPLE_JPG: &[u8] = include_bytes!("../samples/obama.jpg");
+
+    #[test]
+    fn stream_round_trip() {
+        let camouflaged = crate::camouflage_stream(
+            SAMPLE_JPG,
+            "Hello, World!",
+            Some("secret"),
+            Some(1),
+            Vec::new(),
+        )
+        .unwrap();
+        let camouflaged = String::from_utf8(camouflaged).unwrap();
+        assert!(camouflaged.starts_with("Hello, \u{200D}"));
+        assert!(camouflaged.ends_with("World!"));
+
+        let decamouflaged =
+            crate::decamouflage_stream(camouflaged.as_bytes(), Some("secret"), Vec::new()).unwrap();
+        assert_eq!(SAMPLE_JPG, decamouflaged.as_slice());
+
+        assert!(
+            crate::decamouflage_stream(camouflaged.as_bytes(), Some("wrong"), Vec::new()).is_err()
+        );
+    }
+
+    #[test]
+    fn stream_truncated() {
+        let camouflaged =
+            crate::camouflage_stream(SAMPLE_JPG, "Hello, World!", None, Some(1), Vec::new())
+                .unwrap();
+        let decamouflaged =
+            crate::decamouflage_stream(camouflaged.as_slice(), None, Vec::new()).unwrap();
+        assert_eq!(SAMPLE_JPG, decamouflaged.as_slice());
+
+        let truncated = &camouflaged[..camouflaged.len() / 2];
+        assert!(crate::decamouflage_stream(truncated, None, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn stream_flags() {
+        let lorem = include_bytes!("../samples/lorem.txt");
+
+        // a stream without encryption is rejected when a key is expected
+        let plain =
+            crate::camouflage_stream(&lorem[..], "Hello, World!", None, None, Vec::new()).unwrap();
+        assert!(matches!(
+            crate::decamouflage_stream(plain.as_slice(), Some("secret"), Vec::new()),
+            Err(crate::camo::Error::InvalidPayload)
+        ));
+
+        // the stream header flags are authenticated
+        let encrypted = crate::camouflage_stream(
+            &lorem[..],
+            "Hello, World!",
+            Some("secret"),
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+        let start = "Hello, ".len();
+        let mut flipped = encrypted.clone();
+        flipped[start..start + 12].copy_from_slice(crate::encode_to_string(&[0b11]).as_bytes());
+        assert!(
+            crate::decamouflage_stream(encrypted.as_slice(), Some("secret"), Vec::new()).is_ok()
+        );
+        assert!(matches!(
+            crate::decamouflage_stream(flipped.as_slice(), Some("secret"), Vec::new()),
+            Err(crate::camo::Error::Cipher(_))
+        ));
+    }
+}
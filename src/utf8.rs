@@ -0,0 +1,400 @@
+use crate::{Block, BlockBuffer, Compression, Error, CHARS};
+use core::iter::FusedIterator;
+
+/// UTF-8 representations of the zero-width characters
+pub(crate) const UTF8_CHARS: [[u8; 3]; 6] = [
+    [0xE2, 0x80, 0x8C],
+    [0xE2, 0x80, 0x8D],
+    [0xE2, 0x81, 0xA0],
+    [0xE2, 0x81, 0xA2],
+    [0xE2, 0x81, 0xA3],
+    [0xE2, 0x81, 0xA4],
+];
+
+/// UTF-8 representations of every byte encoded without compression
+pub(crate) static UTF8_BLOCKS: [[u8; 12]; 256] = utf8_blocks();
+
+/// Builds the UTF-8 representations of every byte encoded without compression
+const fn utf8_blocks() -> [[u8; 12]; 256] {
+    let mut blocks = [[0; 12]; 256];
+    let mut b = 0;
+    while b < 256 {
+        let mut i = 0;
+        while i < 4 {
+            let c = UTF8_CHARS[(b >> (i * 2)) & 0b11];
+            blocks[b][i * 3] = c[0];
+            blocks[b][i * 3 + 1] = c[1];
+            blocks[b][i * 3 + 2] = c[2];
+            i += 1;
+        }
+        b += 1;
+    }
+    blocks
+}
+
+/// Returns the index of a zero-width character produced by the encoder
+#[inline]
+pub(crate) fn char_index(c: char) -> usize {
+    match c {
+        crate::CHAR0 => 0,
+        crate::CHAR1 => 1,
+        crate::CHAR2 => 2,
+        crate::CHAR3 => 3,
+        crate::CHAR4 => 4,
+        _ => 5,
+    }
+}
+
+/// Returns the index of a zero-width character from its UTF-8 representation
+#[inline]
+pub(crate) fn utf8_index(bytes: [u8; 3]) -> Result<usize, Error> {
+    match bytes {
+        [0xE2, 0x80, 0x8C] => Ok(0),
+        [0xE2, 0x80, 0x8D] => Ok(1),
+        [0xE2, 0x81, 0xA0] => Ok(2),
+        [0xE2, 0x81, 0xA2] => Ok(3),
+        [0xE2, 0x81, 0xA3] => Ok(4),
+        [0xE2, 0x81, 0xA4] => Ok(5),
+        [0xE2, 0x80, b] | [0xE2, 0x81, b] => Err(Error::InvalidByte(b)),
+        [0xE2, b, _] => Err(Error::InvalidByte(b)),
+        [b, ..] => Err(Error::InvalidByte(b)),
+    }
+}
+
+/// Reads the next zero-width character from a UTF-8 byte iterator,
+/// reporting `len` characters in the current block if it ends in the middle of one
+#[inline]
+fn next_char<T: Iterator<Item = u8>>(iter: &mut T, len: usize) -> Option<Result<char, Error>> {
+    let b0 = iter.next()?;
+    let (b1, b2) = match (iter.next(), iter.next()) {
+        (Some(b1), Some(b2)) => (b1, b2),
+        _ => return Some(Err(Error::IncompleteBlock(len))),
+    };
+    Some(utf8_index([b0, b1, b2]).map(|i| CHARS[i]))
+}
+
+/// Converts a byte iterator into a UTF-8 encoded zero-width character iterator
+pub fn encode_utf8<T: Iterator<Item = u8>>(iter: T) -> EncodeUtf8Iter<T> {
+    EncodeUtf8Iter::new(iter)
+}
+
+/// Converts a UTF-8 encoded zero-width character iterator into a byte iterator
+pub fn decode_utf8<T: Iterator<Item = u8>>(iter: T) -> DecodeUtf8Iter<T> {
+    DecodeUtf8Iter::new(iter)
+}
+
+/// Converts a byte iterator into a UTF-8 encoded zero-width character iterator compressed using the provided settings
+pub fn encode_compress_utf8<T: Iterator<Item = u8>>(
+    iter: T,
+    compression: Compression,
+) -> EncodeCompressUtf8Iter<T> {
+    EncodeCompressUtf8Iter::new(iter, compression)
+}
+
+/// Converts a UTF-8 encoded zero-width character iterator into a byte iterator decompressed using the provided settings
+pub fn decode_decompress_utf8<T: Iterator<Item = u8>>(
+    iter: T,
+    compression: Compression,
+) -> DecodeDecompressUtf8Iter<T> {
+    DecodeDecompressUtf8Iter::new(iter, compression)
+}
+
+/// Iterator converting bytes into UTF-8 encoded zero-width characters, returned by [`encode_utf8`]
+#[derive(Debug, Clone)]
+pub struct EncodeUtf8Iter<T: Iterator<Item = u8>> {
+    inner: T,
+    front: BlockBuffer<u8, 12>,
+    back: BlockBuffer<u8, 12>,
+}
+impl<T: Iterator<Item = u8>> EncodeUtf8Iter<T> {
+    /// Creates a UTF-8 encoding iterator over the provided bytes
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            front: BlockBuffer::empty(0),
+            back: BlockBuffer::empty(0),
+        }
+    }
+}
+impl<T: Iterator<Item = u8>> Iterator for EncodeUtf8Iter<T> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(b) = self.front.next() {
+                return Some(b);
+            }
+            match self.inner.next() {
+                Some(b) => self.front = BlockBuffer::new(UTF8_BLOCKS[b as usize], 12),
+                None => return self.back.next(),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.front.len() + self.back.len();
+        let (lower, upper) = self.inner.size_hint();
+        (
+            lower.saturating_mul(12).saturating_add(buffered),
+            upper.and_then(|u| u.checked_mul(12)?.checked_add(buffered)),
+        )
+    }
+}
+impl<T: DoubleEndedIterator<Item = u8>> DoubleEndedIterator for EncodeUtf8Iter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(b) = self.back.next_back() {
+                return Some(b);
+            }
+            match self.inner.next_back() {
+                Some(b) => self.back = BlockBuffer::new(UTF8_BLOCKS[b as usize], 12),
+                None => return self.front.next_back(),
+            }
+        }
+    }
+}
+impl<T: ExactSizeIterator<Item = u8>> ExactSizeIterator for EncodeUtf8Iter<T> {}
+impl<T: FusedIterator<Item = u8>> FusedIterator for EncodeUtf8Iter<T> {}
+
+/// Iterator converting UTF-8 encoded zero-width characters into bytes, returned by [`decode_utf8`]
+#[derive(Debug, Clone)]
+pub struct DecodeUtf8Iter<T: Iterator<Item = u8>> {
+    inner: T,
+}
+impl<T: Iterator<Item = u8>> DecodeUtf8Iter<T> {
+    /// Creates a UTF-8 decoding iterator over the provided bytes
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
+impl<T: Iterator<Item = u8>> Iterator for DecodeUtf8Iter<T> {
+    type Item = Result<u8, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        macro_rules! match_next {
+            ($len:expr) => {
+                match next_char(&mut self.inner, $len) {
+                    Some(Ok(c)) => c,
+                    Some(Err(e)) => return Some(Err(e)),
+                    None if $len == 0 => return None,
+                    None => return Some(Err(Error::IncompleteBlock($len))),
+                }
+            };
+        }
+
+        let c0 = match_next!(0);
+        let c1 = match_next!(1);
+        let c2 = match_next!(2);
+        let c3 = match_next!(3);
+        Some(Block::from_chars([c0, c1, c2, c3]).map(Into::into))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Invalid characters yield an error as soon as they are read
+        let (lower, upper) = self.inner.size_hint();
+        (lower.div_ceil(12), upper.map(|u| u.div_ceil(3)))
+    }
+}
+impl<T: FusedIterator<Item = u8>> FusedIterator for DecodeUtf8Iter<T> {}
+
+/// Iterator converting bytes into compressed UTF-8 encoded zero-width characters,
+/// returned by [`encode_compress_utf8`]
+#[derive(Debug, Clone)]
+pub struct EncodeCompressUtf8Iter<T: Iterator<Item = u8>> {
+    inner: T,
+    compression: Compression,
+    front: BlockBuffer<u8, 12>,
+    back: BlockBuffer<u8, 12>,
+}
+impl<T: Iterator<Item = u8>> EncodeCompressUtf8Iter<T> {
+    /// Creates a UTF-8 encoding iterator over the provided bytes compressed using the provided settings
+    pub fn new(inner: T, compression: Compression) -> Self {
+        Self {
+            inner,
+            compression,
+            front: BlockBuffer::empty(0),
+            back: BlockBuffer::empty(0),
+        }
+    }
+
+    /// Returns the UTF-8 representation of a compressed byte
+    #[inline]
+    fn block(&self, b: u8) -> BlockBuffer<u8, 12> {
+        let (chars, len) = Block::from(b).to_compressed_chars(self.compression);
+        let mut buffer = [0; 12];
+        for (c, dst) in chars.iter().take(len).zip(buffer.chunks_exact_mut(3)) {
+            dst.copy_from_slice(&UTF8_CHARS[char_index(*c)]);
+        }
+        BlockBuffer::new(buffer, len * 3)
+    }
+}
+impl<T: Iterator<Item = u8>> Iterator for EncodeCompressUtf8Iter<T> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(b) = self.front.next() {
+                return Some(b);
+            }
+            match self.inner.next() {
+                Some(b) => self.front = self.block(b),
+                None => return self.back.next(),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.front.len() + self.back.len();
+        let (lower, upper) = self.inner.size_hint();
+        (
+            lower.saturating_mul(6).saturating_add(buffered),
+            upper.and_then(|u| u.checked_mul(12)?.checked_add(buffered)),
+        )
+    }
+}
+impl<T: DoubleEndedIterator<Item = u8>> DoubleEndedIterator for EncodeCompressUtf8Iter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(b) = self.back.next_back() {
+                return Some(b);
+            }
+            match self.inner.next_back() {
+                Some(b) => self.back = self.block(b),
+                None => return self.front.next_back(),
+            }
+        }
+    }
+}
+impl<T: FusedIterator<Item = u8>> FusedIterator for EncodeCompressUtf8Iter<T> {}
+
+/// Iterator converting compressed UTF-8 encoded zero-width characters into bytes,
+/// returned by [`decode_decompress_utf8`]
+#[derive(Debug, Clone)]
+pub struct DecodeDecompressUtf8Iter<T: Iterator<Item = u8>> {
+    inner: T,
+    compression: Compression,
+}
+impl<T: Iterator<Item = u8>> DecodeDecompressUtf8Iter<T> {
+    /// Creates a UTF-8 decoding iterator over the provided bytes decompressed using the provided settings
+    pub fn new(inner: T, compression: Compression) -> Self {
+        Self { inner, compression }
+    }
+}
+impl<T: Iterator<Item = u8>> Iterator for DecodeDecompressUtf8Iter<T> {
+    type Item = Result<u8, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chars = ['\0'; 4];
+        let mut len = 0;
+        let mut ceil = 0;
+
+        while ceil < 4 {
+            chars[len] = match next_char(&mut self.inner, len) {
+                Some(Ok(c)) => c,
+                Some(Err(e)) => return Some(Err(e)),
+                None if len == 0 => return None,
+                None => return Some(Err(Error::IncompleteBlock(len))),
+            };
+            ceil += match chars[len] {
+                crate::CHAR4 | crate::CHAR5 => 2,
+                _ => 1,
+            };
+            len += 1;
+        }
+
+        Some(Block::from_compressed_chars(chars, len, self.compression).map(Into::into))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.inner.size_hint();
+        (lower.div_ceil(12), upper.map(|u| u.div_ceil(3)))
+    }
+}
+impl<T: FusedIterator<Item = u8>> FusedIterator for DecodeDecompressUtf8Iter<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{DecodeDecompressUtf8Iter, DecodeUtf8Iter, EncodeCompressUtf8Iter, EncodeUtf8Iter};
+    use crate::tests::assert_size_hints;
+    use crate::Compression;
+    use proptest::prelude::*;
+
+    static SRC: &[u8] = include_bytes!("./utf8.rs");
+
+    proptest! {
+        #[test]
+        fn utf8_size_hints(data: Vec<u8>, p0 in 0u8..16, p1 in 0u8..16) {
+            let compression = Compression::new(p0, p1).unwrap();
+
+            let encoded: Vec<u8> = crate::encode_utf8(data.iter().copied()).collect();
+            prop_assert_eq!(EncodeUtf8Iter::new(data.iter().copied()).len(), encoded.len());
+            assert_size_hints(EncodeUtf8Iter::new(data.iter().copied()), encoded.len());
+            let mut backward: Vec<u8> = EncodeUtf8Iter::new(data.iter().copied()).rev().collect();
+            backward.reverse();
+            prop_assert_eq!(&backward, &encoded);
+
+            let compressed: Vec<u8> =
+                crate::encode_compress_utf8(data.iter().copied(), compression).collect();
+            assert_size_hints(EncodeCompressUtf8Iter::new(data.iter().copied(), compression), compressed.len());
+            let mut backward: Vec<u8> =
+                EncodeCompressUtf8Iter::new(data.iter().copied(), compression).rev().collect();
+            backward.reverse();
+            prop_assert_eq!(&backward, &compressed);
+
+            // Truncated input exercises the error paths
+            for input in [&encoded[..], &encoded[..encoded.len() / 2], &data[..]].iter() {
+                let len = crate::decode_utf8(input.iter().copied()).count();
+                assert_size_hints(DecodeUtf8Iter::new(input.iter().copied()), len);
+
+                let len = crate::decode_decompress_utf8(input.iter().copied(), compression).count();
+                assert_size_hints(DecodeDecompressUtf8Iter::new(input.iter().copied(), compression), len);
+            }
+        }
+    }
+
+    #[test]
+    fn utf8_round_trip() {
+        let encoded: Vec<u8> = crate::encode_utf8(SRC.iter().copied()).collect();
+        assert!(std::str::from_utf8(&encoded)
+            .unwrap()
+            .chars()
+            .eq(crate::encode(SRC.iter().copied())));
+
+        let decoded = crate::decode_utf8(encoded.into_iter());
+        for (ob, db) in SRC.iter().copied().zip(decoded) {
+            assert_eq!(ob, db.unwrap());
+        }
+    }
+
+    #[test]
+    fn compression_utf8_round_trip() {
+        let (compression, ..) = crate::Compression::optimal(SRC);
+        let encoded: Vec<u8> =
+            crate::encode_compress_utf8(SRC.iter().copied(), compression).collect();
+        assert!(std::str::from_utf8(&encoded)
+            .unwrap()
+            .chars()
+            .eq(crate::encode_compress(SRC.iter().copied(), compression)));
+
+        let decoded = crate::decode_decompress_utf8(encoded.into_iter(), compression);
+        for (ob, db) in SRC.iter().copied().zip(decoded) {
+            assert_eq!(ob, db.unwrap());
+        }
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        let mut decoded = crate::decode_utf8(b"abc".iter().copied());
+        assert_eq!(decoded.next(), Some(Err(crate::Error::InvalidByte(b'a'))));
+
+        // U+200E is not part of the alphabet, only its last byte differs
+        let mut decoded = crate::decode_utf8([0xE2, 0x80, 0x8E].iter().copied());
+        assert_eq!(decoded.next(), Some(Err(crate::Error::InvalidByte(0x8E))));
+
+        let mut decoded = crate::decode_utf8([0xE2, 0x82, 0xAC].iter().copied());
+        assert_eq!(decoded.next(), Some(Err(crate::Error::InvalidByte(0x82))));
+
+        let mut decoded = crate::decode_utf8([0xE2, 0x80].iter().copied());
+        assert_eq!(decoded.next(), Some(Err(crate::Error::IncompleteBlock(0))));
+    }
+}